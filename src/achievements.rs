@@ -0,0 +1,494 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::board::{Collision, Move, MoveResult};
+use crate::gamepad::PadInput;
+use crate::menu::AppState;
+use crate::pieces::{Opponent, Piece, Player};
+use crate::{storage, GameOver, GameSet, RunStats};
+
+const ACHIEVEMENTS_FILE: &str = "achievements.txt";
+const BISHOP_SECS: f32 = 120.;
+const QUEENS_TO_DODGE: u32 = 100;
+const STILL_SECS: f32 = 10.;
+const COLLISIONS_IN_RUN: u32 = 5;
+// seconds an unlock stays on screen
+const TOAST_SECS: f32 = 4.;
+
+/// Milestones tracked across runs, each unlocking a skin or a mode.
+/// Bot and assisted runs don't count.
+pub struct AchievementsPlugin;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Achievement {
+    /// Survive two minutes as a bishop.
+    BishopMarathon,
+    /// See a hundred queens leave the board, over any number of runs.
+    QueenDodger,
+    /// Go ten seconds without moving.
+    Statue,
+    /// Knock five opponents into each other with your own moves in one run.
+    PileUp,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 4] = [
+        Achievement::BishopMarathon,
+        Achievement::QueenDodger,
+        Achievement::Statue,
+        Achievement::PileUp,
+    ];
+
+    fn id(self) -> &'static str {
+        match self {
+            Achievement::BishopMarathon => "bishop-marathon",
+            Achievement::QueenDodger => "queen-dodger",
+            Achievement::Statue => "statue",
+            Achievement::PileUp => "pile-up",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::BishopMarathon => "Bishop marathon",
+            Achievement::QueenDodger => "Queen dodger",
+            Achievement::Statue => "Statue",
+            Achievement::PileUp => "Pile-up",
+        }
+    }
+
+    pub fn description(self) -> String {
+        match self {
+            Achievement::BishopMarathon => format!("Survive {BISHOP_SECS:.0}s as a bishop"),
+            Achievement::QueenDodger => format!("Dodge {QUEENS_TO_DODGE} queens"),
+            Achievement::Statue => format!("Stand still for {STILL_SECS:.0}s"),
+            Achievement::PileUp => format!(
+                "Knock {COLLISIONS_IN_RUN} opponents into each other with your moves in one run"
+            ),
+        }
+    }
+
+    pub fn reward(self) -> Reward {
+        match self {
+            Achievement::BishopMarathon => Reward::Skin(Skin::Gold),
+            Achievement::QueenDodger => Reward::HardMode,
+            Achievement::Statue => Reward::Skin(Skin::Ghost),
+            Achievement::PileUp => Reward::Skin(Skin::Ruby),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reward {
+    Skin(Skin),
+    /// Hard difficulty on the title screen.
+    HardMode,
+}
+
+impl Reward {
+    pub fn describe(self) -> String {
+        match self {
+            Reward::Skin(skin) => format!("{} skin", skin.name()),
+            Reward::HardMode => "hard difficulty".to_string(),
+        }
+    }
+}
+
+/// A tint for the player's piece.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Skin {
+    #[default]
+    Classic,
+    Gold,
+    Ghost,
+    Ruby,
+}
+
+impl Skin {
+    pub const ALL: [Skin; 4] = [Skin::Classic, Skin::Gold, Skin::Ghost, Skin::Ruby];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Skin::Classic => "classic",
+            Skin::Gold => "gold",
+            Skin::Ghost => "ghost",
+            Skin::Ruby => "ruby",
+        }
+    }
+
+    fn tint(self) -> Color {
+        match self {
+            Skin::Classic => Color::WHITE,
+            Skin::Gold => Color::srgb(1., 0.8, 0.3),
+            Skin::Ghost => Color::srgba(0.8, 0.9, 1., 0.45),
+            Skin::Ruby => Color::srgb(1., 0.35, 0.4),
+        }
+    }
+}
+
+/// Sent when an achievement is first earned.
+#[derive(Event)]
+pub struct AchievementUnlocked(pub Achievement);
+
+#[derive(Resource, Default)]
+pub struct Achievements {
+    pub unlocked: Vec<Achievement>,
+    pub queens_dodged: u32,
+    pub skin: Skin,
+    // dodges not yet saved
+    dirty: bool,
+    run: RunProgress,
+}
+
+// what the current run has done so far
+#[derive(Default)]
+struct RunProgress {
+    player: Option<Entity>,
+    started_as: Option<Piece>,
+    last_loc: Option<Vec3>,
+    still_secs: f32,
+    collisions: u32,
+    // opponents as of the last tick, as the ones in this tick's events
+    // may already be gone
+    opponents: HashMap<Entity, Piece>,
+}
+
+impl Achievements {
+    fn load() -> Self {
+        let mut achievements = Self::default();
+        let Some(file) = storage::load(ACHIEVEMENTS_FILE) else {
+            return achievements;
+        };
+        for (name, value) in file.lines().filter_map(|line| line.split_once('=')) {
+            let value = value.trim();
+            match name.trim() {
+                "unlocked" => {
+                    if let Some(achievement) =
+                        Achievement::ALL.into_iter().find(|a| a.id() == value)
+                    {
+                        achievements.unlocked.push(achievement);
+                    }
+                }
+                "queens_dodged" => achievements.queens_dodged = value.parse().unwrap_or_default(),
+                "skin" => {
+                    achievements.skin = Skin::ALL
+                        .into_iter()
+                        .find(|skin| skin.name() == value)
+                        .unwrap_or_default()
+                }
+                _ => (),
+            }
+        }
+        achievements
+    }
+
+    fn save(&mut self) {
+        self.dirty = false;
+        let mut file: Vec<String> = self
+            .unlocked
+            .iter()
+            .map(|a| format!("unlocked={}", a.id()))
+            .collect();
+        file.push(format!("queens_dodged={}", self.queens_dodged));
+        file.push(format!("skin={}", self.skin.name()));
+        storage::save(ACHIEVEMENTS_FILE, &file.join("\n"));
+    }
+
+    pub fn has(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    pub fn has_reward(&self, reward: Reward) -> bool {
+        reward == Reward::Skin(Skin::Classic) || self.unlocked.iter().any(|a| a.reward() == reward)
+    }
+
+    /// Progress towards an achievement not yet earned, for the list.
+    pub fn progress(&self, achievement: Achievement) -> Option<String> {
+        match achievement {
+            Achievement::QueenDodger => Some(format!("{}/{QUEENS_TO_DODGE}", self.queens_dodged)),
+            _ => None,
+        }
+    }
+
+    fn unlock(&mut self, achievement: Achievement, writer: &mut EventWriter<AchievementUnlocked>) {
+        if !self.has(achievement) {
+            self.unlocked.push(achievement);
+            writer.send(AchievementUnlocked(achievement));
+        }
+    }
+}
+
+#[derive(Component)]
+struct ToastText;
+
+#[derive(Component)]
+struct AchievementsUi;
+
+#[derive(Component)]
+struct AchievementsText;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Achievements::load())
+            .add_event::<AchievementUnlocked>()
+            .add_systems(Startup, spawn_toast)
+            .add_systems(FixedUpdate, track_achievements.after(GameSet::Logic))
+            .add_systems(Update, (apply_skin, show_toast))
+            .add_systems(OnEnter(AppState::Achievements), enter_achievements)
+            .add_systems(OnExit(AppState::Achievements), exit_achievements)
+            .add_systems(
+                Update,
+                (achievements_input, update_achievements_text)
+                    .chain()
+                    .run_if(in_state(AppState::Achievements)),
+            );
+    }
+}
+
+fn track_achievements(
+    time: Res<Time>,
+    game_over: Res<GameOver>,
+    stats: Res<RunStats>,
+    player: Query<(Entity, &Piece), With<Player>>,
+    opponents: Query<(Entity, &Piece), With<Opponent>>,
+    mut moves: EventReader<Move>,
+    mut collisions: EventReader<Collision>,
+    mut achievements: ResMut<Achievements>,
+    mut unlocked_writer: EventWriter<AchievementUnlocked>,
+) {
+    let Ok((player, piece)) = player.get_single() else {
+        return;
+    };
+    if achievements.run.player != Some(player) {
+        // a new game
+        achievements.run = RunProgress {
+            player: Some(player),
+            started_as: Some(*piece),
+            ..default()
+        };
+    }
+    if stats.bot || stats.assisted || game_over.0 {
+        // the queen count is saved once a run is over
+        if achievements.dirty {
+            achievements.save();
+        }
+        moves.clear();
+        collisions.clear();
+        achievements.run.opponents = opponents
+            .iter()
+            .map(|(entity, piece)| (entity, *piece))
+            .collect();
+        return;
+    }
+    let before = achievements.unlocked.len();
+    let (mut dodged, mut moved) = (0, false);
+    let run = &mut achievements.run;
+    for event in moves.read() {
+        match event.mov {
+            MoveResult::NewLoc(loc) if event.id == player => {
+                moved |= run.last_loc.is_some_and(|last| last != loc);
+                run.last_loc = Some(loc);
+            }
+            // off the board or down a hole; merged, captured and bombed ones don't count
+            MoveResult::Delete if run.opponents.get(&event.id) == Some(&Piece::Queen) => {
+                dodged += 1;
+            }
+            _ => (),
+        }
+    }
+    // one for each opponent knocked into another, bounced ones included
+    run.collisions += collisions
+        .read()
+        .filter(|collision| collision.by_player)
+        .map(|collision| collision.opponents as u32 - 1)
+        .sum::<u32>();
+    run.still_secs = if moved {
+        0.
+    } else {
+        run.still_secs + time.delta_seconds()
+    };
+    run.opponents = opponents
+        .iter()
+        .map(|(entity, piece)| (entity, *piece))
+        .collect();
+    let (started_as, still_secs, collisions) = (run.started_as, run.still_secs, run.collisions);
+
+    achievements.queens_dodged += dodged;
+    achievements.dirty |= dodged > 0;
+    if started_as == Some(Piece::Bishop) && stats.elapsed >= BISHOP_SECS {
+        achievements.unlock(Achievement::BishopMarathon, &mut unlocked_writer);
+    }
+    if achievements.queens_dodged >= QUEENS_TO_DODGE {
+        achievements.unlock(Achievement::QueenDodger, &mut unlocked_writer);
+    }
+    if still_secs >= STILL_SECS {
+        achievements.unlock(Achievement::Statue, &mut unlocked_writer);
+    }
+    if collisions >= COLLISIONS_IN_RUN {
+        achievements.unlock(Achievement::PileUp, &mut unlocked_writer);
+    }
+    if achievements.unlocked.len() > before {
+        achievements.save();
+    }
+}
+
+fn apply_skin(achievements: Res<Achievements>, mut players: Query<&mut Sprite, With<Player>>) {
+    for mut sprite in players.iter_mut() {
+        let tint = achievements.skin.tint();
+        if sprite.color != tint {
+            sprite.color = tint;
+        }
+    }
+}
+
+fn spawn_toast(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 28.,
+                color: Color::srgb(1., 0.85, 0.2),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(5.),
+            top: Val::Px(5.),
+            ..default()
+        })
+        .with_text_justify(JustifyText::Right),
+        ToastText,
+    ));
+}
+
+fn show_toast(
+    time: Res<Time<Real>>,
+    mut unlocked: EventReader<AchievementUnlocked>,
+    mut shown_for: Local<f32>,
+    mut query: Query<(&mut Text, &mut Visibility), With<ToastText>>,
+) {
+    let Ok((mut text, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    for AchievementUnlocked(achievement) in unlocked.read() {
+        text.sections[0].value = format!(
+            "Achievement: {}\nUnlocked {}",
+            achievement.name(),
+            achievement.reward().describe()
+        );
+        *shown_for = 0.;
+        *visibility = Visibility::Inherited;
+    }
+    *shown_for += time.delta_seconds();
+    if *shown_for > TOAST_SECS {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn enter_achievements(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            AchievementsUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 26.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center)
+                .with_background_color(Color::srgba(0., 0., 0., 0.7)),
+                AchievementsText,
+            ));
+        });
+}
+
+fn exit_achievements(mut commands: Commands, ui: Query<Entity, With<AchievementsUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn achievements_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pad: PadInput,
+    mut achievements: ResMut<Achievements>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) || pad.just_pressed(GamepadButtonType::Select) {
+        next_state.set(AppState::Title);
+        return;
+    }
+    let step = if keyboard_input.just_pressed(KeyCode::ArrowRight)
+        || pad.just_pressed(GamepadButtonType::DPadRight)
+    {
+        1
+    } else if keyboard_input.just_pressed(KeyCode::ArrowLeft)
+        || pad.just_pressed(GamepadButtonType::DPadLeft)
+    {
+        Skin::ALL.len() - 1
+    } else {
+        return;
+    };
+    // cycle through the skins earned so far
+    let current = Skin::ALL
+        .iter()
+        .position(|skin| *skin == achievements.skin)
+        .unwrap_or(0);
+    let next = (1..=Skin::ALL.len())
+        .map(|n| Skin::ALL[(current + n * step) % Skin::ALL.len()])
+        .find(|skin| achievements.has_reward(Reward::Skin(*skin)))
+        .unwrap_or_default();
+    if next != achievements.skin {
+        achievements.skin = next;
+        achievements.save();
+    }
+}
+
+fn update_achievements_text(
+    achievements: Res<Achievements>,
+    mut query: Query<&mut Text, With<AchievementsText>>,
+) {
+    let Ok(mut text) = query.get_single_mut() else {
+        return;
+    };
+    let mut lines = vec!["Achievements".to_string()];
+    for achievement in Achievement::ALL {
+        let status = if achievements.has(achievement) {
+            "[x]".to_string()
+        } else {
+            achievements
+                .progress(achievement)
+                .map_or("[ ]".to_string(), |progress| format!("[{progress}]"))
+        };
+        lines.push(format!(
+            "{status} {}: {}\n    unlocks {}",
+            achievement.name(),
+            achievement.description(),
+            achievement.reward().describe()
+        ));
+    }
+    lines.push(format!(
+        "Left/Right: skin [{}]  Esc: title",
+        achievements.skin.name()
+    ));
+    let message = lines.join("\n");
+    if text.sections[0].value != message {
+        text.sections[0].value = message;
+    }
+}
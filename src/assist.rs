@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+
+use crate::board::{Board, BoardPos, TileType};
+use crate::bots::Bot;
+use crate::pieces::{Piece, Player};
+use crate::{GameOver, PLAYER_MOVE_SPEED, SQUARE_LEN};
+
+// game speed while slow time is on
+pub const ASSIST_SPEED: f32 = 0.6;
+// above this chance of being hit a move is not hinted as safe
+const SAFE_THREAT: f32 = 0.05;
+
+/// Onboarding help chosen on the title screen. Runs with either
+/// option on are marked assisted and kept off the high-score table.
+#[derive(Resource, Default)]
+pub struct AssistSettings {
+    /// Highlight which of the player's moves are safe.
+    pub hints: bool,
+    /// Run the game at `ASSIST_SPEED`.
+    pub slow: bool,
+}
+
+impl AssistSettings {
+    pub fn any(&self) -> bool {
+        self.hints || self.slow
+    }
+
+    pub fn game_speed(&self) -> f32 {
+        if self.slow {
+            ASSIST_SPEED
+        } else {
+            1.
+        }
+    }
+}
+
+/// Tints the player's legal destinations green when safe and red otherwise.
+pub struct AssistPlugin;
+
+#[derive(Component)]
+struct HintTile(BoardPos);
+
+impl Plugin for AssistPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AssistSettings>()
+            .add_systems(Startup, spawn_hint_tiles)
+            .add_systems(Update, update_hint_tiles);
+    }
+}
+
+fn spawn_hint_tiles(mut commands: Commands) {
+    for pos in BoardPos::all() {
+        let mut translation = Board::coord_to_vec(pos);
+        translation.z = 0.6;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::NONE,
+                    custom_size: Some(Vec2::splat(SQUARE_LEN * 0.4)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            HintTile(pos),
+        ));
+    }
+}
+
+fn update_hint_tiles(
+    settings: Res<AssistSettings>,
+    game_over: Res<GameOver>,
+    fixed_time: Res<Time<Fixed>>,
+    board: Query<&Board>,
+    player: Query<(Entity, &Piece), (With<Player>, Without<Bot>)>,
+    mut tiles: Query<(&HintTile, &mut Sprite, &mut Visibility)>,
+) {
+    // (legal destinations, threat map) while a human is playing with hints on
+    let hints = match (
+        settings.hints && !game_over.0,
+        board.get_single(),
+        player.get_single(),
+    ) {
+        (true, Ok(board), Ok((entity, piece))) => board.find(TileType::Player(entity)).map(|pos| {
+            let move_ticks =
+                (PLAYER_MOVE_SPEED / fixed_time.timestep().as_secs_f32()).ceil() as u32;
+            // where each move lands, leaving out those the terrain stops
+            let moves: Vec<BoardPos> = piece
+                .player_moves()
+                .iter()
+                .filter_map(|dir| board.land(pos, *dir, true))
+                .filter(|to| *to != pos)
+                .collect();
+            (moves, board.threat_map(move_ticks))
+        }),
+        _ => None,
+    };
+    for (tile, mut sprite, mut visibility) in tiles.iter_mut() {
+        let Some((moves, threats)) = &hints else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        if moves.contains(&tile.0) {
+            *visibility = Visibility::Inherited;
+            sprite.color = if threats[tile.0.y][tile.0.x] <= SAFE_THREAT {
+                Color::srgba(0.2, 0.9, 0.3, 0.7)
+            } else {
+                Color::srgba(0.9, 0.2, 0.2, 0.7)
+            };
+        } else {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Headless balance runner: plays many seeded games with a bot and
+//! reports survival times, death causes and spawn column histograms.
+//!
+//! cargo run --bin sim -- --games 1000 --seed 1 --bot greedy --format json --out runs.json
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use chess_dodge::board::OppCollision;
+use chess_dodge::bots::Bot;
+use chess_dodge::level::Level;
+use chess_dodge::pawn::{PawnChallenge, PROMOTION_SECS};
+use chess_dodge::pieces::Piece;
+use chess_dodge::spawner::{Difficulty, Spawner};
+use chess_dodge::terrain::obstacle_course;
+use chess_dodge::{GameOver, GamePlugin, GameRng, NewGame, RunStats, N_TILES};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+const TICK: f64 = 1. / 64.;
+
+struct Args {
+    games: u64,
+    seed: u64,
+    max_secs: f32,
+    bot: Bot,
+    collisions: OppCollision,
+    pawn: Option<Piece>,
+    power_ups: bool,
+    obstacles: bool,
+    level: Option<Level>,
+    difficulty: Difficulty,
+    gauntlet: bool,
+    json: bool,
+    out: Option<String>,
+}
+
+struct RunResult {
+    seed: u64,
+    survived: f32,
+    death_by: Option<Piece>,
+    spawn_count: [u32; N_TILES],
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!("usage: sim [--games N] [--seed S] [--max-secs T] [--bot scripted|greedy|lookahead] [--collisions capture|bounce|merge|chain] [--pawn rook|bishop|knight|queen] [--power-ups] [--obstacles] [--level PATH] [--difficulty easy|normal|hard] [--gauntlet] [--format csv|json] [--out PATH]");
+            std::process::exit(2);
+        }
+    };
+    let results: Vec<RunResult> = (0..args.games)
+        .map(|game| run_game(args.seed + game, &args))
+        .collect();
+    let output = if args.json {
+        to_json(&results)
+    } else {
+        to_csv(&results)
+    };
+    match args.out {
+        Some(path) => std::fs::write(&path, output).unwrap_or_else(|err| {
+            eprintln!("could not write {path}: {err}");
+            std::process::exit(1);
+        }),
+        None => print!("{output}"),
+    }
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        games: 1000,
+        seed: 0,
+        max_secs: 600.,
+        bot: Bot::Scripted,
+        collisions: OppCollision::Capture,
+        pawn: None,
+        power_ups: false,
+        obstacles: false,
+        level: None,
+        difficulty: Difficulty::Normal,
+        gauntlet: false,
+        json: false,
+        out: None,
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(flag) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("missing value for {flag}"));
+        match flag.as_str() {
+            "--games" => args.games = value()?.parse().map_err(|_| "bad --games")?,
+            "--seed" => args.seed = value()?.parse().map_err(|_| "bad --seed")?,
+            "--max-secs" => args.max_secs = value()?.parse().map_err(|_| "bad --max-secs")?,
+            "--bot" => match value()?.as_str() {
+                "scripted" => args.bot = Bot::Scripted,
+                "greedy" => args.bot = Bot::Greedy,
+                "lookahead" => args.bot = Bot::Lookahead,
+                other => return Err(format!("unknown bot {other}")),
+            },
+            "--collisions" => match value()?.as_str() {
+                "capture" => args.collisions = OppCollision::Capture,
+                "bounce" => args.collisions = OppCollision::Bounce,
+                "merge" => args.collisions = OppCollision::Merge,
+                "chain" => args.collisions = OppCollision::Chain,
+                other => return Err(format!("unknown collision rule {other}")),
+            },
+            "--pawn" => match value()?.as_str() {
+                "rook" => args.pawn = Some(Piece::Rook),
+                "bishop" => args.pawn = Some(Piece::Bishop),
+                "knight" => args.pawn = Some(Piece::Knight),
+                "queen" => args.pawn = Some(Piece::Queen),
+                other => return Err(format!("unknown promotion {other}")),
+            },
+            "--power-ups" => args.power_ups = true,
+            "--obstacles" => args.obstacles = true,
+            "--level" => {
+                let path = value()?;
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("could not read {path}: {err}"))?;
+                args.level = Some(Level::parse(&text).map_err(|err| format!("{path}: {err}"))?);
+            }
+            "--difficulty" => match value()?.as_str() {
+                "easy" => args.difficulty = Difficulty::Easy,
+                "normal" => args.difficulty = Difficulty::Normal,
+                "hard" => args.difficulty = Difficulty::Hard,
+                other => return Err(format!("unknown difficulty {other}")),
+            },
+            "--gauntlet" => args.gauntlet = true,
+            "--format" => match value()?.as_str() {
+                "csv" => args.json = false,
+                "json" => args.json = true,
+                other => return Err(format!("unknown format {other}")),
+            },
+            "--out" => args.out = Some(value()?),
+            other => return Err(format!("unknown argument {other}")),
+        }
+    }
+    Ok(args)
+}
+
+fn run_game(seed: u64, args: &Args) -> RunResult {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(GamePlugin)
+        .insert_resource(GameRng::seeded(seed))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            TICK,
+        )))
+        .insert_resource(Time::<Fixed>::from_seconds(TICK));
+    app.finish();
+    app.cleanup();
+    let level = args
+        .level
+        .as_ref()
+        .map_or_else(NewGame::default, Level::new_game);
+    app.world_mut().send_event(NewGame {
+        bot: Some(args.bot),
+        collisions: args.collisions,
+        pawn: args
+            .pawn
+            .map(|piece| PawnChallenge::new(piece, Some(PROMOTION_SECS))),
+        power_ups: args.power_ups,
+        difficulty: args.difficulty,
+        gauntlet: args.gauntlet,
+        terrain: if args.obstacles {
+            obstacle_course()
+        } else {
+            level.terrain.clone()
+        },
+        ..level
+    });
+    loop {
+        app.update();
+        let world = app.world();
+        if world.resource::<GameOver>().0 || world.resource::<RunStats>().elapsed >= args.max_secs {
+            break;
+        }
+    }
+    let world = app.world_mut();
+    let stats = world.resource::<RunStats>();
+    let (survived, death_by) = (stats.elapsed, stats.death_by);
+    let spawn_count = world.query::<&Spawner>().single(world).spawn_count;
+    RunResult {
+        seed,
+        survived,
+        death_by,
+        spawn_count,
+    }
+}
+
+fn death_name(death_by: Option<Piece>) -> String {
+    death_by.map_or("none".to_string(), |piece| format!("{piece:?}"))
+}
+
+fn to_csv(results: &[RunResult]) -> String {
+    let mut out = String::from("seed,survived_secs,death_by");
+    for col in 0..N_TILES {
+        let _ = write!(out, ",spawns_col{col}");
+    }
+    out.push('\n');
+    for run in results {
+        let _ = write!(
+            out,
+            "{},{:.3},{}",
+            run.seed,
+            run.survived,
+            death_name(run.death_by)
+        );
+        for count in run.spawn_count {
+            let _ = write!(out, ",{count}");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn to_json(results: &[RunResult]) -> String {
+    let mut survived: Vec<f32> = results.iter().map(|run| run.survived).collect();
+    survived.sort_by(f32::total_cmp);
+    let percentile = |p: f32| {
+        survived
+            .get(((survived.len() as f32 - 1.) * p).round() as usize)
+            .copied()
+            .unwrap_or(0.)
+    };
+    let mean = survived.iter().sum::<f32>() / survived.len().max(1) as f32;
+    // 10 second buckets
+    let mut buckets: BTreeMap<u32, u32> = BTreeMap::new();
+    for secs in &survived {
+        *buckets.entry((*secs / 10.) as u32 * 10).or_default() += 1;
+    }
+    let mut deaths: BTreeMap<String, u32> = BTreeMap::new();
+    let mut columns = [0u64; N_TILES];
+    for run in results {
+        *deaths.entry(death_name(run.death_by)).or_default() += 1;
+        for (col, count) in run.spawn_count.iter().enumerate() {
+            columns[col] += *count as u64;
+        }
+    }
+
+    let mut out = String::from("{\n");
+    let _ = writeln!(out, "  \"games\": {},", results.len());
+    let _ = writeln!(
+        out,
+        "  \"survival\": {{\"mean\": {mean:.3}, \"min\": {:.3}, \"p10\": {:.3}, \"p50\": {:.3}, \"p90\": {:.3}, \"max\": {:.3}}},",
+        percentile(0.),
+        percentile(0.1),
+        percentile(0.5),
+        percentile(0.9),
+        percentile(1.),
+    );
+    let buckets: Vec<String> = buckets
+        .iter()
+        .map(|(start, count)| format!("\"{start}\": {count}"))
+        .collect();
+    let _ = writeln!(out, "  \"survival_histogram\": {{{}}},", buckets.join(", "));
+    let deaths: Vec<String> = deaths
+        .iter()
+        .map(|(piece, count)| format!("\"{piece}\": {count}"))
+        .collect();
+    let _ = writeln!(out, "  \"death_causes\": {{{}}},", deaths.join(", "));
+    let columns: Vec<String> = columns.iter().map(|count| count.to_string()).collect();
+    let _ = writeln!(out, "  \"spawn_columns\": [{}]", columns.join(", "));
+    out.push_str("}\n");
+    out
+}
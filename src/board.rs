@@ -0,0 +1,1106 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::pieces::Piece;
+use crate::terrain::Terrain;
+use crate::{N_TILES, TILE_DIS};
+
+// caps path enumeration in threat_map at 3^8 paths per opponent
+const MAX_THREAT_MOVES: usize = 8;
+// board coordinate of the world origin, between the middle tiles
+const CENTER: f32 = (N_TILES - 1) as f32 / 2.;
+
+#[derive(Event, Debug)]
+pub struct MoveReq {
+    pub id: TileType,
+    pub mov: Direction,
+}
+
+#[derive(Event, Debug, PartialEq)]
+pub struct Move {
+    pub id: Entity,
+    pub mov: MoveResult,
+}
+
+#[derive(Event, Deref)]
+pub struct ToDelete {
+    pub id: Entity,
+}
+
+/// Recoverable mismatches between events, entities and the board. They
+/// are logged and shown by the debug overlay while the game carries on.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardError {
+    /// A move was requested for a piece that isn't on the board.
+    MissingPiece(TileType),
+    /// An event or the board named an entity that no longer exists.
+    StaleEntity(Entity),
+    /// An opponent piece has no moves, so it was removed.
+    NoMoves(Piece),
+    /// An opponent spawn was requested outside the board.
+    BadColumn(usize),
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoardError::MissingPiece(id) => {
+                write!(f, "move requested for {id:?}, which is not on the board")
+            }
+            BoardError::StaleEntity(entity) => write!(f, "{entity:?} no longer exists"),
+            BoardError::NoMoves(piece) => write!(f, "opponent {piece:?} has no moves"),
+            BoardError::BadColumn(col) => {
+                write!(f, "spawn requested in column {col}, off the board")
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MoveResult {
+    NewLoc(Vec3),
+    Delete,
+    // deleted because the given entity took the tile
+    Captured(Entity),
+    // deleted and absorbed into the given opponent, which is promoted
+    Merged(Entity),
+}
+
+/// What happens to an opponent that loses a contest for a tile to another
+/// opponent. The player always loses to opponents and is captured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OppCollision {
+    /// The loser is removed.
+    #[default]
+    Capture,
+    /// The loser stays where it was.
+    Bounce,
+    /// The loser is absorbed and the winner promoted, see `Piece::merged`.
+    Merge,
+    /// Every opponent in the collision is removed.
+    Chain,
+}
+
+/// Opponents meeting in a contest settled by `Board::resolve`.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct Collision {
+    /// The tile they met on.
+    pub at: BoardPos,
+    pub opponents: usize,
+    pub rule: OppCollision,
+    /// Whether the player's move this tick led to it.
+    pub by_player: bool,
+}
+
+/// Everything `Board::resolve` decided in one tick.
+#[derive(Default)]
+pub struct Resolution {
+    pub moves: Vec<Move>,
+    pub errors: Vec<BoardError>,
+    pub collisions: Vec<Collision>,
+}
+
+#[derive(Component)]
+pub struct Board {
+    board: [[TileType; N_TILES]; N_TILES],
+    // where each piece on `board` is, kept in sync by `set_tile`
+    positions: HashMap<Entity, BoardPos>,
+    // what each tile is made of, indexed like `board`
+    terrain: [[Terrain; N_TILES]; N_TILES],
+    pub collisions: OppCollision,
+    /// Whether the player has a shield up, set before each `resolve`,
+    /// which spends it, as does an opponent spawning onto the player.
+    pub shielded: bool,
+    // refreshed every tick by track_opponents, used for threat prediction
+    pub opp_timers: HashMap<Entity, OppTimer>,
+}
+
+/// How an opponent moves, with its timer measured in fixed ticks.
+#[derive(Clone, Copy, Debug)]
+pub struct OppTimer {
+    pub piece: Piece,
+    pub edge: Edge,
+    pub next_move: u32,
+    pub period: u32,
+}
+
+/// A tile on the board, counted from the top left. The constructors
+/// only ever produce positions on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BoardPos {
+    pub x: usize,
+    pub y: usize,
+}
+
+// a piece's move for the tick, as gathered by `Board::resolve`
+struct Intent {
+    id: TileType,
+    entity: Entity,
+    from: BoardPos,
+    // None when the move leaves the board
+    to: Option<BoardPos>,
+    // the player, or a piece sent back by a contest the player was in
+    by_player: bool,
+}
+
+// the first conflict among pieces not yet removed, as (winner, losers):
+// a head-on swap, else the contest for the first tile from the top left
+fn next_conflict(intents: &[Intent], fates: &[Option<MoveResult>]) -> Option<(usize, Vec<usize>)> {
+    let live: Vec<usize> = (0..intents.len()).filter(|i| fates[*i].is_none()).collect();
+    let mut starts: HashMap<BoardPos, usize> = HashMap::new();
+    let mut contests: HashMap<BoardPos, Vec<usize>> = HashMap::new();
+    for &i in &live {
+        starts.insert(intents[i].from, i);
+        if let Some(to) = intents[i].to {
+            contests.entry(to).or_default().push(i);
+        }
+    }
+    // each piece starts on its own tile, so a swap has one partner
+    let swap = live.iter().find_map(|&i| {
+        let a = &intents[i];
+        let j = *starts.get(&a.to?)?;
+        let b = &intents[j];
+        (a.moved() && b.moved() && b.to == Some(a.from)).then_some((i, j))
+    });
+    if let Some((i, j)) = swap {
+        return Some(if intents[i].beats(&intents[j]) {
+            (i, vec![j])
+        } else {
+            (j, vec![i])
+        });
+    }
+    let (_, contenders) = contests
+        .into_iter()
+        .filter(|(_, contenders)| contenders.len() > 1)
+        .min_by_key(|(pos, _)| (pos.y, pos.x))?;
+    let winner = contenders.iter().copied().reduce(|best, i| {
+        if intents[i].beats(&intents[best]) {
+            i
+        } else {
+            best
+        }
+    })?;
+    let losers = contenders.into_iter().filter(|i| *i != winner).collect();
+    Some((winner, losers))
+}
+
+impl Intent {
+    fn moved(&self) -> bool {
+        self.to != Some(self.from)
+    }
+
+    // contest priority, see `Board::resolve`
+    fn beats(&self, other: &Intent) -> bool {
+        let key = |intent: &Intent| {
+            (
+                matches!(intent.id, TileType::Player(_)),
+                intent.moved(),
+                intent.from.y,
+                intent.from.x,
+            )
+        };
+        key(self) < key(other)
+    }
+}
+
+/// Per-tile chance of an opponent, indexed `[y][x]` like the board.
+pub type ThreatMap = [[f32; N_TILES]; N_TILES];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileType {
+    Empty,
+    Player(Entity),
+    Opponent(Entity),
+    // a pickup waiting for the player, see `powerups`
+    PowerUp(Entity),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    UpLeft,
+    UpRight,
+    Left,
+    Right,
+    Down,
+    DownLeft,
+    DownRight,
+    UpLeftWide,
+    UpLeftNarrow,
+    UpRightNarrow,
+    UpRightWide,
+    DownRightWide,
+    DownRightNarrow,
+    DownLeftNarrow,
+    DownLeftWide,
+    None,
+}
+
+/// The side of the board an opponent came in from. Opponent moves are
+/// written for the top edge and turned to head away from their own.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Edge {
+    #[default]
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    /// The tile `lane` tiles along this edge, from the top or the left.
+    pub fn tile(self, lane: usize) -> BoardPos {
+        match self {
+            Edge::Top => BoardPos::new(lane, 0),
+            Edge::Bottom => BoardPos::new(lane, N_TILES - 1),
+            Edge::Left => BoardPos::new(0, lane),
+            Edge::Right => BoardPos::new(N_TILES - 1, lane),
+        }
+    }
+
+    /// A move written for the top edge, turned to head away from this one.
+    pub fn turn(self, dir: Direction) -> Direction {
+        let (dx, dy) = dir.delta();
+        let turned = match self {
+            Edge::Top => (dx, dy),
+            Edge::Bottom => (dx, -dy),
+            Edge::Left => (dy, dx),
+            Edge::Right => (-dy, dx),
+        };
+        Direction::ALL
+            .into_iter()
+            .find(|dir| dir.delta() == turned)
+            .unwrap_or(Direction::None)
+    }
+
+    /// Moves an opponent of `piece` coming from this edge picks between.
+    pub fn moves(self, piece: Piece) -> Vec<Direction> {
+        piece
+            .opp_moves()
+            .iter()
+            .map(|dir| self.turn(*dir))
+            .collect()
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board {
+            board: [[TileType::Empty; N_TILES]; N_TILES],
+            positions: HashMap::new(),
+            terrain: [[Terrain::Floor; N_TILES]; N_TILES],
+            collisions: OppCollision::default(),
+            shielded: false,
+            opp_timers: HashMap::new(),
+        }
+    }
+}
+
+impl TileType {
+    pub fn entity(self) -> Option<Entity> {
+        match self {
+            TileType::Empty => None,
+            TileType::Player(entity) | TileType::Opponent(entity) | TileType::PowerUp(entity) => {
+                Some(entity)
+            }
+        }
+    }
+
+    /// Players and opponents, which move and contest tiles.
+    pub fn is_piece(self) -> bool {
+        matches!(self, TileType::Player(_) | TileType::Opponent(_))
+    }
+}
+
+impl BoardPos {
+    /// Panics if `(x, y)` is off the board.
+    pub const fn new(x: usize, y: usize) -> Self {
+        assert!(x < N_TILES && y < N_TILES, "position off the board");
+        Self { x, y }
+    }
+
+    pub fn checked(x: i32, y: i32) -> Option<Self> {
+        let on_board = |val: i32| (0..N_TILES as i32).contains(&val);
+        (on_board(x) && on_board(y)).then_some(Self {
+            x: x as usize,
+            y: y as usize,
+        })
+    }
+
+    pub fn offset(self, (dx, dy): (i32, i32)) -> Option<Self> {
+        Self::checked(self.x as i32 + dx, self.y as i32 + dy)
+    }
+
+    /// Where `dir` leads from here, if it stays on the board.
+    pub fn step(self, dir: Direction) -> Option<Self> {
+        self.offset(dir.delta())
+    }
+
+    /// Every tile, row by row from the top.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..N_TILES).flat_map(|y| (0..N_TILES).map(move |x| Self { x, y }))
+    }
+}
+
+impl Direction {
+    pub const ALL: [Direction; 17] = [
+        Direction::Up,
+        Direction::UpLeft,
+        Direction::UpRight,
+        Direction::Left,
+        Direction::Right,
+        Direction::Down,
+        Direction::DownLeft,
+        Direction::DownRight,
+        Direction::UpLeftWide,
+        Direction::UpLeftNarrow,
+        Direction::UpRightNarrow,
+        Direction::UpRightWide,
+        Direction::DownRightWide,
+        Direction::DownRightNarrow,
+        Direction::DownLeftNarrow,
+        Direction::DownLeftWide,
+        Direction::None,
+    ];
+
+    /// Tile offset of the move as `(x, y)`, with y growing down the board.
+    pub fn delta(self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::DownLeft => (-1, 1),
+            Direction::DownRight => (1, 1),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (1, -1),
+            Direction::UpLeftWide => (-2, -1),
+            Direction::UpLeftNarrow => (-1, -2),
+            Direction::UpRightNarrow => (1, -2),
+            Direction::UpRightWide => (2, -1),
+            Direction::DownRightWide => (2, 1),
+            Direction::DownRightNarrow => (1, 2),
+            Direction::DownLeftNarrow => (-1, 2),
+            Direction::DownLeftWide => (-2, 1),
+            Direction::None => (0, 0),
+        }
+    }
+}
+
+impl Board {
+    /// Centre of the tile in world space, at the depth pieces are drawn.
+    pub fn coord_to_vec(pos: BoardPos) -> Vec3 {
+        Vec3::new(
+            (pos.x as f32 - CENTER) * TILE_DIS,
+            (CENTER - pos.y as f32) * TILE_DIS,
+            1.,
+        )
+    }
+
+    /// Inverse of `coord_to_vec`: the tile nearest a world position, if on the board.
+    pub fn vec_to_coord(world: Vec2) -> Option<BoardPos> {
+        let x = (world.x / TILE_DIS + CENTER).round();
+        let y = (CENTER - world.y / TILE_DIS).round();
+        BoardPos::checked(x as i32, y as i32)
+    }
+
+    /// The tile under a window position. Going through the camera keeps
+    /// this right however the window is scaled.
+    pub fn screen_to_coord(
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        screen: Vec2,
+    ) -> Option<BoardPos> {
+        camera
+            .viewport_to_world_2d(camera_transform, screen)
+            .and_then(Self::vec_to_coord)
+    }
+
+    pub fn tile(&self, pos: BoardPos) -> TileType {
+        self.board[pos.y][pos.x]
+    }
+
+    pub fn terrain(&self, pos: BoardPos) -> Terrain {
+        self.terrain[pos.y][pos.x]
+    }
+
+    pub fn set_terrain(&mut self, pos: BoardPos, terrain: Terrain) {
+        self.terrain[pos.y][pos.x] = terrain;
+    }
+
+    // the other end of a teleporter
+    fn partner(&self, pos: BoardPos) -> Option<BoardPos> {
+        let Terrain::Teleporter(id) = self.terrain(pos) else {
+            return None;
+        };
+        BoardPos::all()
+            .find(|other| *other != pos && self.terrain(*other) == Terrain::Teleporter(id))
+    }
+
+    /// Where a piece moving `dir` from `from` ends up once the terrain has
+    /// had its say, or None if it leaves the board or falls in a hole.
+    /// Walls stop a move, ice slides the piece one more tile the same way,
+    /// and teleporters then send it on to their partner. The player never
+    /// leaves the board or enters a hole, and stays put instead.
+    pub fn land(&self, from: BoardPos, dir: Direction, player: bool) -> Option<BoardPos> {
+        let blocked = |pos: BoardPos| match self.terrain(pos) {
+            Terrain::Wall => true,
+            Terrain::Hole => player,
+            _ => false,
+        };
+        let stay = player.then_some(from);
+        let mut to = match from.step(dir) {
+            Some(to) if blocked(to) => return Some(from),
+            Some(to) => to,
+            None => return stay,
+        };
+        if self.terrain(to) == Terrain::Ice && dir != Direction::None {
+            let (dx, dy) = dir.delta();
+            match to.offset((dx.signum(), dy.signum())) {
+                Some(next) if !blocked(next) => to = next,
+                // an opponent slides off the board, the player stops at the edge
+                None if !player => return None,
+                _ => (),
+            }
+        }
+        match self.terrain(to) {
+            Terrain::Hole => None,
+            Terrain::Teleporter(_) if to != from => Some(self.partner(to).unwrap_or(to)),
+            _ => Some(to),
+        }
+    }
+
+    pub fn row(&self, y: usize) -> &[TileType; N_TILES] {
+        &self.board[y]
+    }
+
+    pub fn place_piece(&mut self, pos: BoardPos, entity: TileType) -> bool {
+        if let TileType::Empty = self.tile(pos) {
+            self.set_tile(pos, entity);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Puts `entity` on the tile, replacing whatever was there. A piece
+    /// already elsewhere on the board moves, so each piece is on one tile.
+    pub fn set_tile(&mut self, pos: BoardPos, entity: TileType) {
+        if let Some(old) = self.tile(pos).entity() {
+            self.positions.remove(&old);
+        }
+        if let Some(new) = entity.entity() {
+            if let Some(prev) = self.positions.insert(new, pos) {
+                self.board[prev.y][prev.x] = TileType::Empty;
+            }
+        }
+        self.board[pos.y][pos.x] = entity;
+    }
+
+    /// Takes the piece off the board, returning where it was.
+    pub fn remove(&mut self, entity: Entity) -> Option<BoardPos> {
+        let pos = self.positions.remove(&entity)?;
+        self.board[pos.y][pos.x] = TileType::Empty;
+        Some(pos)
+    }
+
+    /// Mirrors the board and its terrain top to bottom. Pieces keep moving
+    /// the way they were, so opponents now head towards what was behind them.
+    pub fn flip(&mut self) {
+        self.board.reverse();
+        self.terrain.reverse();
+        for pos in self.positions.values_mut() {
+            pos.y = N_TILES - 1 - pos.y;
+        }
+    }
+
+    /// Every piece on the board and where it is.
+    pub fn pieces(&self) -> impl Iterator<Item = (Entity, BoardPos)> + '_ {
+        self.positions
+            .iter()
+            .filter(|(_, pos)| self.tile(**pos).is_piece())
+            .map(|(entity, pos)| (*entity, *pos))
+    }
+
+    /// Every power-up on the board and where it is.
+    pub fn power_ups(&self) -> impl Iterator<Item = (Entity, BoardPos)> + '_ {
+        self.positions
+            .iter()
+            .filter(|(_, pos)| matches!(self.tile(**pos), TileType::PowerUp(_)))
+            .map(|(entity, pos)| (*entity, *pos))
+    }
+
+    pub fn find(&self, id: TileType) -> Option<BoardPos> {
+        let pos = *self.positions.get(&id.entity()?)?;
+        // the same entity as a player and an opponent are different pieces
+        (self.tile(pos) == id).then_some(pos)
+    }
+
+    // debug builds verify the index against a full scan of the grid once
+    // a tick, after `resolve`, rather than on every `set_tile`
+    fn check_index(&self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        let pieces = BoardPos::all()
+            .filter(|pos| self.tile(*pos).entity().is_some())
+            .count();
+        assert_eq!(
+            pieces,
+            self.positions.len(),
+            "board index out of sync with the grid"
+        );
+        for (entity, pos) in &self.positions {
+            assert_eq!(
+                self.tile(*pos).entity(),
+                Some(*entity),
+                "board index has {entity:?} at {pos:?}"
+            );
+        }
+    }
+
+    /// Applies one tick of `MoveReq`s together, so the outcome doesn't
+    /// depend on the order requests arrive in. All moves are gathered
+    /// first, then conflicts are settled by these rules:
+    ///
+    /// 1. Moves go where `land` says, so the terrain applies first. An
+    ///    opponent leaving the board or falling in a hole is deleted.
+    /// 2. Two pieces swapping tiles meet head-on and contest the meeting.
+    /// 3. Pieces ending on the same tile contest it, even if one of them
+    ///    stayed put. A piece moving into a tile its occupant is leaving
+    ///    (a chain) is no conflict.
+    ///
+    /// Contests are won by an opponent over the player, then by a piece
+    /// that stayed put over one that moved, then by the piece that started
+    /// nearest the top of the board and then the left. A losing player is
+    /// captured unless `shielded`, when the shield takes out the winner
+    /// instead, before any chain, and the player carries on with its move.
+    /// Losing opponents are dealt with by `collisions`. Winners go where
+    /// they were heading. A collision between opponents is the
+    /// player's doing if the player was in the contest, or if one of them
+    /// was bounced there by a contest that was.
+    ///
+    /// Pieces without a request stay put, and requests for pieces missing
+    /// from the board are skipped and returned as errors. Power-ups don't
+    /// move, and a piece ending on one replaces it.
+    pub fn resolve<'a>(&mut self, reqs: impl IntoIterator<Item = &'a MoveReq>) -> Resolution {
+        let mut resolution = Resolution::default();
+        let mut dirs: HashMap<Entity, Direction> = HashMap::new();
+        for req in reqs {
+            let Some(entity) = req.id.entity() else {
+                continue;
+            };
+            if self.find(req.id).is_none() {
+                resolution.errors.push(BoardError::MissingPiece(req.id));
+                continue;
+            }
+            dirs.insert(entity, req.mov);
+        }
+
+        // phase one: where every piece wants to go, sorted into board order
+        // as the index has none
+        let mut intents: Vec<Intent> = self
+            .pieces()
+            .map(|(entity, from)| {
+                let id = self.tile(from);
+                let dir = dirs.get(&entity).copied().unwrap_or(Direction::None);
+                Intent {
+                    id,
+                    entity,
+                    from,
+                    to: self.land(from, dir, matches!(id, TileType::Player(_))),
+                    by_player: matches!(id, TileType::Player(_)),
+                }
+            })
+            .collect();
+        intents.sort_by_key(|intent| (intent.from.y, intent.from.x));
+
+        // phase two: settle conflicts until none are left, as bounced
+        // pieces can start new ones on the tiles they return to
+        let mut fates: Vec<Option<MoveResult>> = (0..intents.len()).map(|_| None).collect();
+        while let Some((winner, losers)) = next_conflict(&intents, &fates) {
+            let winner_entity = intents[winner].entity;
+            let by_player =
+                intents[winner].by_player || losers.iter().any(|loser| intents[*loser].by_player);
+            intents[winner].by_player = by_player;
+            let player = losers.iter().find_map(|loser| match intents[*loser].id {
+                TileType::Player(entity) => Some(entity),
+                _ => None,
+            });
+            let shield = player.filter(|_| self.shielded);
+            let mut opponents = 1;
+            let mut chained = Vec::new();
+            for loser in losers {
+                intents[loser].by_player = by_player;
+                opponents += usize::from(matches!(intents[loser].id, TileType::Opponent(_)));
+                let captured = Some(MoveResult::Captured(winner_entity));
+                match (intents[loser].id, self.collisions) {
+                    (TileType::Player(_), _) if shield.is_some() => (),
+                    (TileType::Player(_), _) | (_, OppCollision::Capture) => {
+                        fates[loser] = captured
+                    }
+                    (_, OppCollision::Bounce) => intents[loser].to = Some(intents[loser].from),
+                    (_, OppCollision::Merge) => {
+                        fates[loser] = Some(MoveResult::Merged(winner_entity))
+                    }
+                    (_, OppCollision::Chain) => {
+                        fates[loser] = captured;
+                        chained.push(intents[loser].entity);
+                    }
+                }
+            }
+            if let (true, Some(at)) = (opponents > 1, intents[winner].to) {
+                resolution.collisions.push(Collision {
+                    at,
+                    opponents,
+                    rule: self.collisions,
+                    by_player,
+                });
+            }
+            if let Some(player) = shield {
+                self.shielded = false;
+                fates[winner] = Some(MoveResult::Captured(player));
+            } else if let Some(first) = chained.first() {
+                // a chain takes the winner down with the opponents it hit
+                fates[winner] = Some(MoveResult::Captured(*first));
+            }
+        }
+
+        // lift every piece before putting the survivors down, so moves
+        // into tiles being left don't clobber each other
+        for intent in &intents {
+            self.remove(intent.entity);
+        }
+        let mut new_locs = Vec::new();
+        for (intent, fate) in intents.iter().zip(fates) {
+            let id = intent.entity;
+            match (fate, intent.to) {
+                (Some(mov), _) => resolution.moves.push(Move { id, mov }),
+                (None, None) => resolution.moves.push(Move {
+                    id,
+                    mov: MoveResult::Delete,
+                }),
+                (None, Some(to)) => {
+                    self.set_tile(to, intent.id);
+                    new_locs.push(Move {
+                        id,
+                        mov: MoveResult::NewLoc(Self::coord_to_vec(to)),
+                    });
+                }
+            }
+        }
+        // removals first, so a capture's killer hasn't moved on yet
+        resolution.moves.extend(new_locs);
+        self.check_index();
+        resolution
+    }
+
+    fn opponents(&self) -> impl Iterator<Item = (BoardPos, Option<&OppTimer>)> + '_ {
+        BoardPos::all().filter_map(|pos| match self.tile(pos) {
+            TileType::Opponent(entity) => Some((pos, self.opp_timers.get(&entity))),
+            _ => None,
+        })
+    }
+
+    /// Chance of each tile holding an opponent exactly `ticks` ticks from
+    /// now. Each opponent move is treated as an independent uniform pick
+    /// between the piece's options; pieces without timer info stay put.
+    pub fn occupancy(&self, ticks: u32) -> ThreatMap {
+        let mut free: ThreatMap = [[1.; N_TILES]; N_TILES];
+        for (pos, timer) in self.opponents() {
+            let mut dist: ThreatMap = [[0.; N_TILES]; N_TILES];
+            dist[pos.y][pos.x] = 1.;
+            if let Some(timer) = timer {
+                let options = timer.edge.moves(timer.piece);
+                // every piece has left the board after N_TILES moves
+                for _ in 0..timer.moves_within(ticks).min(N_TILES) {
+                    let mut next: ThreatMap = [[0.; N_TILES]; N_TILES];
+                    for (y, row) in dist.iter().enumerate() {
+                        for (x, chance) in
+                            row.iter().enumerate().filter(|(_, chance)| **chance > 0.)
+                        {
+                            for dir in &options {
+                                // pieces that leave the board are gone
+                                if let Some(next_pos) = self.land(BoardPos::new(x, y), *dir, false)
+                                {
+                                    next[next_pos.y][next_pos.x] += chance / options.len() as f32;
+                                }
+                            }
+                        }
+                    }
+                    dist = next;
+                }
+            }
+            for y in 0..N_TILES {
+                for x in 0..N_TILES {
+                    free[y][x] *= 1. - dist[y][x];
+                }
+            }
+        }
+        free.map(|row| row.map(|chance| 1. - chance))
+    }
+
+    /// Chance of each tile being occupied by an opponent at any point in
+    /// the next `lookahead_ticks` ticks, including where opponents are now.
+    pub fn threat_map(&self, lookahead_ticks: u32) -> ThreatMap {
+        fn walk(
+            board: &Board,
+            pos: BoardPos,
+            options: &[Direction],
+            moves: usize,
+            chance: f32,
+            seen: u64,
+            visits: &mut ThreatMap,
+        ) {
+            let bit = 1 << (pos.y * N_TILES + pos.x);
+            if seen & bit == 0 {
+                visits[pos.y][pos.x] += chance;
+            }
+            if moves == 0 {
+                return;
+            }
+            for dir in options {
+                if let Some(next) = board.land(pos, *dir, false) {
+                    walk(
+                        board,
+                        next,
+                        options,
+                        moves - 1,
+                        chance / options.len() as f32,
+                        seen | bit,
+                        visits,
+                    );
+                }
+            }
+        }
+
+        let mut free: ThreatMap = [[1.; N_TILES]; N_TILES];
+        for (pos, timer) in self.opponents() {
+            let mut visits: ThreatMap = [[0.; N_TILES]; N_TILES];
+            match timer {
+                Some(timer) => {
+                    let moves = timer.moves_within(lookahead_ticks).min(MAX_THREAT_MOVES);
+                    walk(
+                        self,
+                        pos,
+                        &timer.edge.moves(timer.piece),
+                        moves,
+                        1.,
+                        0,
+                        &mut visits,
+                    );
+                }
+                None => visits[pos.y][pos.x] = 1.,
+            }
+            for y in 0..N_TILES {
+                for x in 0..N_TILES {
+                    free[y][x] *= 1. - visits[y][x].min(1.);
+                }
+            }
+        }
+        free.map(|row| row.map(|chance| 1. - chance))
+    }
+}
+
+impl OppTimer {
+    /// Number of moves the opponent makes in the next `ticks` ticks.
+    pub fn moves_within(&self, ticks: u32) -> usize {
+        if ticks < self.next_move {
+            0
+        } else {
+            1 + ((ticks - self.next_move) / self.period.max(1)) as usize
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(n: u32) -> Entity {
+        Entity::from_raw(n)
+    }
+
+    // places each piece, requests its move unless it is `None`, and resolves
+    fn resolve(pieces: &[(TileType, (usize, usize), Direction)]) -> Resolution {
+        let mut board = Board::default();
+        for (id, (x, y), _) in pieces {
+            board.set_tile(BoardPos::new(*x, *y), *id);
+        }
+        let reqs: Vec<MoveReq> = pieces
+            .iter()
+            .filter(|(_, _, mov)| *mov != Direction::None)
+            .map(|(id, _, mov)| MoveReq { id: *id, mov: *mov })
+            .collect();
+        board.resolve(&reqs)
+    }
+
+    fn result(resolution: &Resolution, id: Entity) -> &MoveResult {
+        &resolution
+            .moves
+            .iter()
+            .find(|mov| mov.id == id)
+            .unwrap()
+            .mov
+    }
+
+    fn at(x: usize, y: usize) -> MoveResult {
+        MoveResult::NewLoc(Board::coord_to_vec(BoardPos::new(x, y)))
+    }
+
+    #[test]
+    fn swaps_meet_head_on() {
+        let (player, opp) = (entity(1), entity(2));
+        let resolution = resolve(&[
+            (TileType::Player(player), (3, 3), Direction::Up),
+            (TileType::Opponent(opp), (3, 2), Direction::Down),
+        ]);
+        assert_eq!(*result(&resolution, player), MoveResult::Captured(opp));
+        assert_eq!(*result(&resolution, opp), at(3, 3));
+
+        let (left, right) = (entity(3), entity(4));
+        let resolution = resolve(&[
+            (TileType::Opponent(left), (2, 4), Direction::Right),
+            (TileType::Opponent(right), (3, 4), Direction::Left),
+        ]);
+        assert_eq!(*result(&resolution, right), MoveResult::Captured(left));
+        assert_eq!(*result(&resolution, left), at(3, 4));
+    }
+
+    #[test]
+    fn pieces_ending_on_one_tile_contest_it() {
+        let (left, right) = (entity(1), entity(2));
+        let resolution = resolve(&[
+            (TileType::Opponent(left), (2, 2), Direction::DownRight),
+            (TileType::Opponent(right), (4, 2), Direction::DownLeft),
+        ]);
+        assert_eq!(*result(&resolution, right), MoveResult::Captured(left));
+        assert_eq!(*result(&resolution, left), at(3, 3));
+
+        // one that stays put beats one moving in, wherever it started
+        let (still, falling) = (entity(3), entity(4));
+        let resolution = resolve(&[
+            (TileType::Opponent(still), (3, 3), Direction::None),
+            (TileType::Opponent(falling), (3, 2), Direction::Down),
+        ]);
+        assert_eq!(*result(&resolution, falling), MoveResult::Captured(still));
+        assert_eq!(*result(&resolution, still), at(3, 3));
+    }
+
+    #[test]
+    fn chains_move_into_vacated_tiles() {
+        let opps = [entity(1), entity(2), entity(3)];
+        let resolution = resolve(&[
+            (TileType::Opponent(opps[0]), (3, 2), Direction::Down),
+            (TileType::Opponent(opps[1]), (3, 3), Direction::Down),
+            (TileType::Opponent(opps[2]), (3, 4), Direction::Down),
+        ]);
+        assert_eq!(*result(&resolution, opps[0]), at(3, 3));
+        assert_eq!(*result(&resolution, opps[1]), at(3, 4));
+        assert_eq!(*result(&resolution, opps[2]), at(3, 5));
+        assert!(resolution.errors.is_empty());
+    }
+
+    #[test]
+    fn opponents_beat_the_player() {
+        let (player, opp) = (entity(1), entity(2));
+        let resolution = resolve(&[
+            (TileType::Player(player), (2, 3), Direction::Right),
+            (TileType::Opponent(opp), (3, 2), Direction::Down),
+        ]);
+        assert_eq!(*result(&resolution, player), MoveResult::Captured(opp));
+        assert_eq!(*result(&resolution, opp), at(3, 3));
+
+        // even when the player stays put
+        let resolution = resolve(&[
+            (TileType::Player(player), (3, 3), Direction::None),
+            (TileType::Opponent(opp), (3, 2), Direction::Down),
+        ]);
+        assert_eq!(*result(&resolution, player), MoveResult::Captured(opp));
+        assert_eq!(*result(&resolution, opp), at(3, 3));
+    }
+
+    #[test]
+    fn collisions_know_if_the_player_led_to_them() {
+        let (player, left, right) = (entity(1), entity(2), entity(3));
+        let mut board = Board {
+            collisions: OppCollision::Chain,
+            ..default()
+        };
+        board.set_tile(BoardPos::new(3, 4), TileType::Player(player));
+        board.set_tile(BoardPos::new(2, 2), TileType::Opponent(left));
+        board.set_tile(BoardPos::new(4, 2), TileType::Opponent(right));
+        let reqs = [
+            MoveReq {
+                id: TileType::Opponent(left),
+                mov: Direction::DownRight,
+            },
+            MoveReq {
+                id: TileType::Opponent(right),
+                mov: Direction::DownLeft,
+            },
+        ];
+        // next to the player, but not its doing
+        let resolution = board.resolve(&reqs);
+        assert_eq!(
+            resolution.collisions,
+            [Collision {
+                at: BoardPos::new(3, 3),
+                opponents: 2,
+                rule: OppCollision::Chain,
+                by_player: false,
+            }]
+        );
+        assert_eq!(*result(&resolution, left), MoveResult::Captured(right));
+        assert_eq!(*result(&resolution, right), MoveResult::Captured(left));
+
+        // the player moving into the meeting sets it off
+        board.set_tile(BoardPos::new(2, 2), TileType::Opponent(left));
+        board.set_tile(BoardPos::new(4, 2), TileType::Opponent(right));
+        let reqs = [
+            MoveReq {
+                id: TileType::Player(player),
+                mov: Direction::Up,
+            },
+            MoveReq {
+                id: TileType::Opponent(left),
+                mov: Direction::DownRight,
+            },
+            MoveReq {
+                id: TileType::Opponent(right),
+                mov: Direction::DownLeft,
+            },
+        ];
+        let resolution = board.resolve(&reqs);
+        assert!(resolution.collisions[0].by_player);
+    }
+
+    #[test]
+    fn shields_win_before_chains() {
+        let (player, left, right) = (entity(1), entity(2), entity(3));
+        let mut board = Board {
+            collisions: OppCollision::Chain,
+            shielded: true,
+            ..default()
+        };
+        board.set_tile(BoardPos::new(3, 3), TileType::Player(player));
+        board.set_tile(BoardPos::new(2, 2), TileType::Opponent(left));
+        board.set_tile(BoardPos::new(3, 2), TileType::Opponent(right));
+        let reqs = [
+            MoveReq {
+                id: TileType::Opponent(left),
+                mov: Direction::DownRight,
+            },
+            MoveReq {
+                id: TileType::Opponent(right),
+                mov: Direction::Down,
+            },
+        ];
+        let resolution = board.resolve(&reqs);
+        assert_eq!(*result(&resolution, left), MoveResult::Captured(player));
+        assert_eq!(*result(&resolution, right), MoveResult::Captured(left));
+        assert_eq!(*result(&resolution, player), at(3, 3));
+        assert!(resolution.collisions[0].by_player);
+        assert!(!board.shielded);
+        assert_eq!(
+            board.find(TileType::Player(player)),
+            Some(BoardPos::new(3, 3))
+        );
+
+        // spent, so the next capture takes the player
+        board.set_tile(BoardPos::new(3, 2), TileType::Opponent(right));
+        let resolution = board.resolve(&reqs[1..]);
+        assert_eq!(*result(&resolution, player), MoveResult::Captured(right));
+    }
+
+    #[test]
+    fn request_order_does_not_matter() {
+        let reqs = [
+            MoveReq {
+                id: TileType::Player(entity(1)),
+                mov: Direction::Up,
+            },
+            MoveReq {
+                id: TileType::Opponent(entity(2)),
+                mov: Direction::Down,
+            },
+            MoveReq {
+                id: TileType::Opponent(entity(3)),
+                mov: Direction::DownLeft,
+            },
+            MoveReq {
+                id: TileType::Opponent(entity(4)),
+                mov: Direction::Down,
+            },
+            MoveReq {
+                id: TileType::Opponent(entity(5)),
+                mov: Direction::Left,
+            },
+        ];
+        let start = [(2, 4), (2, 3), (3, 2), (5, 5), (6, 6)];
+        let run = |order: &[usize]| {
+            let mut board = Board::default();
+            for (req, (x, y)) in reqs.iter().zip(start) {
+                board.set_tile(BoardPos::new(x, y), req.id);
+            }
+            let resolution = board.resolve(order.iter().map(|i| &reqs[*i]));
+            let tiles: Vec<TileType> = BoardPos::all().map(|pos| board.tile(pos)).collect();
+            (resolution.moves, tiles)
+        };
+
+        // every order, by Heap's algorithm
+        let mut order: Vec<usize> = (0..reqs.len()).collect();
+        let mut counters = vec![0; order.len()];
+        let expected = run(&order);
+        let mut i = 0;
+        while i < order.len() {
+            if counters[i] < i {
+                order.swap(if i % 2 == 0 { 0 } else { counters[i] }, i);
+                assert_eq!(run(&order), expected, "order {order:?}");
+                counters[i] += 1;
+                i = 0;
+            } else {
+                counters[i] = 0;
+                i += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn set_tile_moves_a_piece() {
+        let mut board = Board::default();
+        let player = TileType::Player(entity(1));
+        board.set_tile(BoardPos::new(2, 3), player);
+        board.set_tile(BoardPos::new(4, 3), player);
+        board.check_index();
+        assert_eq!(board.tile(BoardPos::new(2, 3)), TileType::Empty);
+        assert_eq!(board.find(player), Some(BoardPos::new(4, 3)));
+    }
+
+    #[test]
+    fn set_tile_replaces_a_piece() {
+        let mut board = Board::default();
+        let opponent = TileType::Opponent(entity(1));
+        let power_up = TileType::PowerUp(entity(2));
+        board.set_tile(BoardPos::new(5, 5), power_up);
+        board.set_tile(BoardPos::new(5, 5), opponent);
+        board.check_index();
+        assert_eq!(board.find(power_up), None);
+        assert_eq!(board.find(opponent), Some(BoardPos::new(5, 5)));
+        board.set_tile(BoardPos::new(5, 5), TileType::Empty);
+        board.check_index();
+        assert_eq!(board.find(opponent), None);
+    }
+
+    #[test]
+    fn find_checks_the_kind_of_piece() {
+        let mut board = Board::default();
+        board.set_tile(BoardPos::new(0, 0), TileType::Player(entity(1)));
+        board.set_tile(BoardPos::new(1, 0), TileType::Opponent(entity(2)));
+        assert_eq!(board.find(TileType::Opponent(entity(1))), None);
+        assert_eq!(board.find(TileType::Player(entity(2))), None);
+        assert_eq!(board.find(TileType::PowerUp(entity(1))), None);
+        assert_eq!(board.find(TileType::Empty), None);
+    }
+}
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+
+use crate::board::{Board, BoardPos, Direction, MoveReq, ThreatMap, TileType};
+use crate::pieces::{Piece, Player};
+use crate::powerups::Dashing;
+use crate::N_TILES;
+
+// number of player moves the lookahead bot plans ahead
+const LOOKAHEAD_SLICES: usize = 4;
+// chance of a spawn landing on any given top row tile
+const SPAWN_DANGER: f32 = 0.15;
+
+/// Drives the player piece in place of `player_input`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bot {
+    /// Waits on its row and sidesteps pieces in the two tiles above it.
+    Scripted,
+    /// Moves to the adjacent tile least likely to be hit by the next opponent moves.
+    Greedy,
+    /// Plans a few player moves ahead against every possible opponent move.
+    Lookahead,
+}
+
+pub fn bot_input(
+    time: Res<Time>,
+    mut query: Query<(&mut Player, Entity, &Piece, &Bot, Option<&mut Dashing>)>,
+    board: Query<&Board>,
+    mut move_req_writer: EventWriter<MoveReq>,
+) {
+    let board = board.single();
+    for (mut player, entity, piece, bot, mut dashing) in query.iter_mut() {
+        // ticks between two player moves
+        let move_ticks = (player.timer_dur / time.delta_seconds()).ceil() as u32;
+        let id = TileType::Player(entity);
+        let mut mov = Direction::None;
+        let ready = player.ready(time.delta());
+        if let Some(dir) = dashing.as_mut().and_then(|dash| dash.follow_up.take()) {
+            mov = dir;
+        } else if let (true, Some(pos)) = (ready, board.find(id)) {
+            mov = match bot {
+                Bot::Scripted => scripted_move(board, pos),
+                Bot::Greedy => greedy_move(board, *piece, pos, move_ticks),
+                Bot::Lookahead => lookahead_move(board, *piece, pos, move_ticks),
+            };
+            if mov != Direction::None {
+                player.moved();
+                if let Some(dash) = &mut dashing {
+                    dash.follow_up = Some(mov);
+                }
+            }
+        }
+        move_req_writer.send(MoveReq { id, mov });
+    }
+}
+
+fn scripted_move(board: &Board, BoardPos { x, y }: BoardPos) -> Direction {
+    let is_opponent = |x, y| matches!(board.tile(BoardPos::new(x, y)), TileType::Opponent(_));
+    let threatened = |x: usize| (y.saturating_sub(2)..y).any(|row| is_opponent(x, row));
+    let free = |x: usize| !threatened(x) && !is_opponent(x, y);
+    if !threatened(x) {
+        Direction::None
+    } else if x > 0 && free(x - 1) {
+        Direction::Left
+    } else if x + 1 < N_TILES && free(x + 1) {
+        Direction::Right
+    } else {
+        Direction::None
+    }
+}
+
+// staying put first so ties keep the bot still
+fn candidates(board: &Board, piece: Piece, pos: BoardPos) -> Vec<(Direction, BoardPos)> {
+    std::iter::once(Direction::None)
+        .chain(piece.player_moves().iter().copied())
+        .filter_map(|dir| board.land(pos, dir, true).map(|new| (dir, new)))
+        .collect()
+}
+
+fn greedy_move(board: &Board, piece: Piece, pos: BoardPos, move_ticks: u32) -> Direction {
+    let danger = with_spawns(board.threat_map(move_ticks));
+    candidates(board, piece, pos)
+        .into_iter()
+        .min_by(|a, b| danger[a.1.y][a.1.x].total_cmp(&danger[b.1.y][b.1.x]))
+        .map_or(Direction::None, |(dir, _)| dir)
+}
+
+// the board can't predict spawns, so assume some danger on the top row
+fn with_spawns(mut threats: ThreatMap) -> ThreatMap {
+    for chance in threats[0].iter_mut() {
+        *chance = chance.max(SPAWN_DANGER);
+    }
+    threats
+}
+
+fn lookahead_move(board: &Board, piece: Piece, pos: BoardPos, move_ticks: u32) -> Direction {
+    let slices: Vec<ThreatMap> = (0..=LOOKAHEAD_SLICES)
+        .map(|slice| with_spawns(board.occupancy(slice as u32 * move_ticks)))
+        .collect();
+    // a tile is unsafe if an opponent is there before or after the move
+    let danger = |slice: usize, BoardPos { x, y }| slices[slice - 1][y][x].max(slices[slice][y][x]);
+    // survival[y][x]: chance of living through the remaining slices from (x, y)
+    let mut survival: ThreatMap = [[1.; N_TILES]; N_TILES];
+    for slice in (1..=LOOKAHEAD_SLICES).rev() {
+        let mut next: ThreatMap = [[0.; N_TILES]; N_TILES];
+        for (y, row) in next.iter_mut().enumerate() {
+            for (x, value) in row.iter_mut().enumerate() {
+                let best = if slice == LOOKAHEAD_SLICES {
+                    1.
+                } else {
+                    candidates(board, piece, BoardPos::new(x, y))
+                        .iter()
+                        .map(|(_, next)| survival[next.y][next.x])
+                        .fold(0., f32::max)
+                };
+                *value = (1. - danger(slice, BoardPos::new(x, y))) * best;
+            }
+        }
+        survival = next;
+    }
+    candidates(board, piece, pos)
+        .into_iter()
+        .rev()
+        .max_by(|a, b| survival[a.1.y][a.1.x].total_cmp(&survival[b.1.y][b.1.x]))
+        .map_or(Direction::None, |(dir, _)| dir)
+}
@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+
+use crate::assist::AssistSettings;
+use crate::gamepad::PadInput;
+use crate::level::{GameWon, Level};
+use crate::menu::{attract_game, AppState, ATTRACT_RESTART};
+use crate::{storage, GameOver, NewGame, RunStats};
+
+const PROGRESS_FILE: &str = "campaign.txt";
+
+/// The handcrafted levels in `assets/levels`, played in order. Beating a
+/// level unlocks the next, and progress is saved between sessions.
+pub struct CampaignPlugin;
+
+#[derive(Resource)]
+pub struct Campaign {
+    pub levels: Vec<Level>,
+    /// Levels that can be played, counting from the first.
+    pub unlocked: usize,
+    /// Whether each level has been beaten.
+    pub completed: Vec<bool>,
+    /// Highlighted on the level list.
+    pub selected: usize,
+    /// The level being played, None on the level list.
+    pub playing: Option<usize>,
+}
+
+impl Campaign {
+    fn load() -> Self {
+        let levels = Level::campaign();
+        let file = storage::load(PROGRESS_FILE).unwrap_or_default();
+        let setting = |setting: &str| {
+            file.lines()
+                .filter_map(|line| line.split_once('='))
+                .find(|(name, _)| name.trim() == setting)
+                .map(|(_, value)| value.trim().to_string())
+        };
+        let unlocked = usize::clamp(
+            setting("unlocked")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1),
+            1,
+            levels.len(),
+        );
+        let completed = match setting("completed") {
+            Some(value) => {
+                let beaten: Vec<usize> = value
+                    .split(',')
+                    .filter_map(|n| n.trim().parse().ok())
+                    .collect();
+                (0..levels.len()).map(|n| beaten.contains(&n)).collect()
+            }
+            // progress saved before completion was, where every unlocked
+            // level but the last had been beaten
+            None => (0..levels.len()).map(|n| n + 1 < unlocked).collect(),
+        };
+        Self {
+            unlocked,
+            completed,
+            selected: 0,
+            playing: None,
+            levels,
+        }
+    }
+
+    fn save(&self) {
+        let completed: Vec<String> = self
+            .completed
+            .iter()
+            .enumerate()
+            .filter(|(_, done)| **done)
+            .map(|(n, _)| n.to_string())
+            .collect();
+        storage::save(
+            PROGRESS_FILE,
+            &format!(
+                "unlocked={}\ncompleted={}\n",
+                self.unlocked,
+                completed.join(",")
+            ),
+        );
+    }
+
+    // marks the level beaten and unlocks the one after it
+    fn complete(&mut self, level: usize) {
+        let unlocked = (level + 2).min(self.levels.len());
+        if unlocked > self.unlocked || !self.completed[level] {
+            self.unlocked = self.unlocked.max(unlocked);
+            self.completed[level] = true;
+            self.save();
+        }
+    }
+}
+
+#[derive(Component)]
+struct CampaignUi;
+
+#[derive(Component)]
+struct CampaignText;
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Campaign::load())
+            .add_systems(OnEnter(AppState::Campaign), enter_campaign)
+            .add_systems(OnExit(AppState::Campaign), exit_campaign)
+            .add_systems(
+                Update,
+                (unlock_levels, campaign_input, update_campaign_text)
+                    .chain()
+                    .run_if(in_state(AppState::Campaign)),
+            );
+    }
+}
+
+fn enter_campaign(mut commands: Commands, mut campaign: ResMut<Campaign>) {
+    campaign.playing = None;
+    campaign.selected = campaign.unlocked - 1;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            CampaignUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 32.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center)
+                .with_background_color(Color::srgba(0., 0., 0., 0.7)),
+                CampaignText,
+            ));
+        });
+}
+
+fn exit_campaign(mut commands: Commands, ui: Query<Entity, With<CampaignUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn unlock_levels(mut won: EventReader<GameWon>, mut campaign: ResMut<Campaign>) {
+    for _ in won.read() {
+        if let Some(level) = campaign.playing {
+            campaign.complete(level);
+        }
+    }
+}
+
+fn start_level(
+    level: usize,
+    campaign: &mut Campaign,
+    assist: &AssistSettings,
+    new_game: &mut EventWriter<NewGame>,
+    virtual_time: &mut Time<Virtual>,
+) {
+    campaign.playing = Some(level);
+    virtual_time.set_relative_speed(assist.game_speed());
+    new_game.send(NewGame {
+        assisted: assist.any(),
+        ..campaign.levels[level].new_game()
+    });
+}
+
+fn campaign_input(
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pad: PadInput,
+    game_over: Res<GameOver>,
+    stats: Res<RunStats>,
+    assist: Res<AssistSettings>,
+    mut campaign: ResMut<Campaign>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut new_game: EventWriter<NewGame>,
+    mut restart: Local<f32>,
+) {
+    let back =
+        keyboard_input.just_pressed(KeyCode::Escape) || pad.just_pressed(GamepadButtonType::Select);
+    let confirm =
+        keyboard_input.just_pressed(KeyCode::Enter) || pad.just_pressed(GamepadButtonType::Start);
+    let Some(level) = campaign.playing else {
+        if back {
+            next_state.set(AppState::Title);
+        } else if confirm && campaign.selected < campaign.unlocked {
+            let selected = campaign.selected;
+            start_level(
+                selected,
+                &mut campaign,
+                &assist,
+                &mut new_game,
+                &mut virtual_time,
+            );
+        } else if keyboard_input.just_pressed(KeyCode::ArrowUp)
+            || pad.just_pressed(GamepadButtonType::DPadUp)
+        {
+            campaign.selected = campaign.selected.saturating_sub(1);
+        } else if keyboard_input.just_pressed(KeyCode::ArrowDown)
+            || pad.just_pressed(GamepadButtonType::DPadDown)
+        {
+            campaign.selected = (campaign.selected + 1).min(campaign.levels.len() - 1);
+        } else if game_over.0 {
+            *restart += time.delta_seconds();
+            if *restart > ATTRACT_RESTART {
+                *restart = 0.;
+                new_game.send(attract_game());
+            }
+        }
+        return;
+    };
+    if back {
+        // leave the level for the list, with a bot playing behind it
+        campaign.playing = None;
+        campaign.selected = level;
+        virtual_time.set_relative_speed(1.);
+        new_game.send(attract_game());
+    } else if game_over.0 && confirm {
+        let next = if stats.won && level + 1 < campaign.levels.len() {
+            level + 1
+        } else {
+            level
+        };
+        start_level(
+            next,
+            &mut campaign,
+            &assist,
+            &mut new_game,
+            &mut virtual_time,
+        );
+    }
+}
+
+fn update_campaign_text(
+    campaign: Res<Campaign>,
+    game_over: Res<GameOver>,
+    stats: Res<RunStats>,
+    mut query: Query<(&mut Text, &mut Visibility), With<CampaignText>>,
+) {
+    let Ok((mut text, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    let message = match (campaign.playing, game_over.0) {
+        (None, _) => {
+            let mut lines = vec!["Campaign".to_string()];
+            for (n, level) in campaign.levels.iter().enumerate() {
+                let cursor = if n == campaign.selected { ">" } else { " " };
+                let goal = level
+                    .goal
+                    .map_or("Survive".to_string(), |goal| goal.to_string());
+                let status = if campaign.completed[n] {
+                    " [done]"
+                } else if n < campaign.unlocked {
+                    ""
+                } else {
+                    " [locked]"
+                };
+                lines.push(format!(
+                    "{cursor} {}. {}: {goal}{status}",
+                    n + 1,
+                    level.name
+                ));
+            }
+            lines.push("Up/Down: choose  Enter: play  Esc: title".to_string());
+            lines.join("\n")
+        }
+        // keep the board clear while playing
+        (Some(_), false) => String::new(),
+        (Some(level), true) if stats.won && level + 1 == campaign.levels.len() => {
+            "Campaign complete!\nEnter: play again  Esc: levels".to_string()
+        }
+        (Some(_), true) if stats.won => {
+            "Level complete!\nEnter: next level  Esc: levels".to_string()
+        }
+        (Some(level), true) => format!(
+            "Caught after {:.1}s\n{}\nEnter: retry  Esc: levels",
+            stats.elapsed,
+            campaign.levels[level]
+                .goal
+                .map_or(String::new(), |goal| goal.to_string()),
+        ),
+    };
+    *visibility = if message.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    if text.sections[0].value != message {
+        text.sections[0].value = message;
+    }
+}
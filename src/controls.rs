@@ -0,0 +1,522 @@
+use bevy::prelude::*;
+
+use crate::gamepad::{PadInput, PadSettings};
+use crate::menu::AppState;
+use crate::storage;
+
+const SETTINGS_FILE: &str = "controls.txt";
+const DEADZONE_STEP: f32 = 0.05;
+pub const DEFAULT_CHORD_WINDOW: f32 = 0.08;
+const MAX_CHORD_WINDOW: f32 = 0.2;
+const CHORD_STEP: f32 = 0.02;
+
+/// Player moves that can be bound to a key.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeftWide,
+    UpLeftNarrow,
+    UpRightNarrow,
+    UpRightWide,
+    DownLeftWide,
+    DownLeftNarrow,
+    DownRightNarrow,
+    DownRightWide,
+}
+
+const ACTIONS: [Action; 12] = [
+    Action::Up,
+    Action::Down,
+    Action::Left,
+    Action::Right,
+    Action::UpLeftWide,
+    Action::UpLeftNarrow,
+    Action::UpRightNarrow,
+    Action::UpRightWide,
+    Action::DownLeftWide,
+    Action::DownLeftNarrow,
+    Action::DownRightNarrow,
+    Action::DownRightWide,
+];
+
+impl Action {
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::UpLeftWide => "Knight up-left wide",
+            Action::UpLeftNarrow => "Knight up-left narrow",
+            Action::UpRightNarrow => "Knight up-right narrow",
+            Action::UpRightWide => "Knight up-right wide",
+            Action::DownLeftWide => "Knight down-left wide",
+            Action::DownLeftNarrow => "Knight down-left narrow",
+            Action::DownRightNarrow => "Knight down-right narrow",
+            Action::DownRightWide => "Knight down-right wide",
+        }
+    }
+
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+/// Keyboard layout used to name keys. Bindings are physical key
+/// positions, so every preset puts the moves under the same fingers;
+/// the layout decides which letters menus and prompts show.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Layout {
+    #[default]
+    Qwerty,
+    Azerty,
+    Dvorak,
+}
+
+#[derive(Resource)]
+pub struct KeyBindings {
+    pub layout: Layout,
+    keys: [KeyCode; ACTIONS.len()],
+    /// Seconds apart the two keys of a diagonal may be pressed.
+    pub chord_window: f32,
+}
+
+const DEFAULT_KEYS: [KeyCode; ACTIONS.len()] = [
+    KeyCode::KeyW,
+    KeyCode::KeyS,
+    KeyCode::KeyA,
+    KeyCode::KeyD,
+    KeyCode::KeyU,
+    KeyCode::KeyI,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::Semicolon,
+];
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            layout: Layout::Qwerty,
+            chord_window: DEFAULT_CHORD_WINDOW,
+            keys: DEFAULT_KEYS,
+        }
+    }
+}
+
+// keys that can be bound, with their save file names and US labels
+const BINDABLE: [(KeyCode, &str, &str); 49] = [
+    (KeyCode::KeyA, "KeyA", "A"),
+    (KeyCode::KeyB, "KeyB", "B"),
+    (KeyCode::KeyC, "KeyC", "C"),
+    (KeyCode::KeyD, "KeyD", "D"),
+    (KeyCode::KeyE, "KeyE", "E"),
+    (KeyCode::KeyF, "KeyF", "F"),
+    (KeyCode::KeyG, "KeyG", "G"),
+    (KeyCode::KeyH, "KeyH", "H"),
+    (KeyCode::KeyI, "KeyI", "I"),
+    (KeyCode::KeyJ, "KeyJ", "J"),
+    (KeyCode::KeyK, "KeyK", "K"),
+    (KeyCode::KeyL, "KeyL", "L"),
+    (KeyCode::KeyM, "KeyM", "M"),
+    (KeyCode::KeyN, "KeyN", "N"),
+    (KeyCode::KeyO, "KeyO", "O"),
+    (KeyCode::KeyP, "KeyP", "P"),
+    (KeyCode::KeyQ, "KeyQ", "Q"),
+    (KeyCode::KeyR, "KeyR", "R"),
+    (KeyCode::KeyS, "KeyS", "S"),
+    (KeyCode::KeyT, "KeyT", "T"),
+    (KeyCode::KeyU, "KeyU", "U"),
+    (KeyCode::KeyV, "KeyV", "V"),
+    (KeyCode::KeyW, "KeyW", "W"),
+    (KeyCode::KeyX, "KeyX", "X"),
+    (KeyCode::KeyY, "KeyY", "Y"),
+    (KeyCode::KeyZ, "KeyZ", "Z"),
+    (KeyCode::Digit0, "Digit0", "0"),
+    (KeyCode::Digit1, "Digit1", "1"),
+    (KeyCode::Digit2, "Digit2", "2"),
+    (KeyCode::Digit3, "Digit3", "3"),
+    (KeyCode::Digit4, "Digit4", "4"),
+    (KeyCode::Digit5, "Digit5", "5"),
+    (KeyCode::Digit6, "Digit6", "6"),
+    (KeyCode::Digit7, "Digit7", "7"),
+    (KeyCode::Digit8, "Digit8", "8"),
+    (KeyCode::Digit9, "Digit9", "9"),
+    (KeyCode::Semicolon, "Semicolon", ";"),
+    (KeyCode::Comma, "Comma", ","),
+    (KeyCode::Period, "Period", "."),
+    (KeyCode::Slash, "Slash", "/"),
+    (KeyCode::Quote, "Quote", "'"),
+    (KeyCode::BracketLeft, "BracketLeft", "["),
+    (KeyCode::BracketRight, "BracketRight", "]"),
+    (KeyCode::Minus, "Minus", "-"),
+    (KeyCode::Space, "Space", "Space"),
+    (KeyCode::ArrowUp, "ArrowUp", "Up arrow"),
+    (KeyCode::ArrowDown, "ArrowDown", "Down arrow"),
+    (KeyCode::ArrowLeft, "ArrowLeft", "Left arrow"),
+    (KeyCode::ArrowRight, "ArrowRight", "Right arrow"),
+];
+
+// labels that differ from the US layout
+const AZERTY_LABELS: [(KeyCode, &str); 6] = [
+    (KeyCode::KeyQ, "A"),
+    (KeyCode::KeyW, "Z"),
+    (KeyCode::KeyA, "Q"),
+    (KeyCode::KeyZ, "W"),
+    (KeyCode::Semicolon, "M"),
+    (KeyCode::KeyM, ","),
+];
+
+const DVORAK_LABELS: [(KeyCode, &str); 32] = [
+    (KeyCode::KeyQ, "'"),
+    (KeyCode::KeyW, ","),
+    (KeyCode::KeyE, "."),
+    (KeyCode::KeyR, "P"),
+    (KeyCode::KeyT, "Y"),
+    (KeyCode::KeyY, "F"),
+    (KeyCode::KeyU, "G"),
+    (KeyCode::KeyI, "C"),
+    (KeyCode::KeyO, "R"),
+    (KeyCode::KeyP, "L"),
+    (KeyCode::BracketLeft, "/"),
+    (KeyCode::BracketRight, "="),
+    (KeyCode::KeyS, "O"),
+    (KeyCode::KeyD, "E"),
+    (KeyCode::KeyF, "U"),
+    (KeyCode::KeyG, "I"),
+    (KeyCode::KeyH, "D"),
+    (KeyCode::KeyJ, "H"),
+    (KeyCode::KeyK, "T"),
+    (KeyCode::KeyL, "N"),
+    (KeyCode::Semicolon, "S"),
+    (KeyCode::Quote, "-"),
+    (KeyCode::KeyZ, ";"),
+    (KeyCode::KeyX, "Q"),
+    (KeyCode::KeyC, "J"),
+    (KeyCode::KeyV, "K"),
+    (KeyCode::KeyB, "X"),
+    (KeyCode::KeyN, "B"),
+    (KeyCode::Comma, "W"),
+    (KeyCode::Period, "V"),
+    (KeyCode::Slash, "Z"),
+    (KeyCode::Minus, "["),
+];
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys[action as usize]
+    }
+
+    pub fn label(&self, action: Action) -> String {
+        self.key_label(self.key(action))
+    }
+
+    fn key_label(&self, key: KeyCode) -> String {
+        let layout_labels: &[(KeyCode, &str)] = match self.layout {
+            Layout::Qwerty => &[],
+            Layout::Azerty => &AZERTY_LABELS,
+            Layout::Dvorak => &DVORAK_LABELS,
+        };
+        let layout_label = layout_labels
+            .iter()
+            .find(|(code, _)| *code == key)
+            .map(|(_, label)| *label);
+        let us_label = BINDABLE
+            .iter()
+            .find(|(code, _, _)| *code == key)
+            .map(|(_, _, label)| *label);
+        layout_label
+            .or(us_label)
+            .map_or(format!("{key:?}"), str::to_string)
+    }
+
+    /// Replaces `{Action}` placeholders such as `{Up}` with key labels.
+    pub fn fill_prompt(&self, prompt: &str) -> String {
+        let mut filled = prompt.to_string();
+        for action in ACTIONS {
+            filled = filled.replace(&format!("{{{}}}", action.id()), &self.label(action));
+        }
+        filled
+    }
+
+    pub fn preset(layout: Layout) -> Self {
+        Self {
+            layout,
+            keys: DEFAULT_KEYS,
+            chord_window: DEFAULT_CHORD_WINDOW,
+        }
+    }
+
+    /// Binds `key` to `action`. A key already used by another action is
+    /// swapped onto it so no two actions share a key; that action is returned.
+    pub fn rebind(&mut self, action: Action, key: KeyCode) -> Option<Action> {
+        let old = self.key(action);
+        let conflict = ACTIONS
+            .iter()
+            .copied()
+            .find(|other| *other != action && self.key(*other) == key);
+        if let Some(other) = conflict {
+            self.keys[other as usize] = old;
+        }
+        self.keys[action as usize] = key;
+        conflict
+    }
+
+    fn load() -> Self {
+        storage::load(SETTINGS_FILE).map_or_else(Self::default, |file| Self::parse(&file))
+    }
+
+    // lines that don't parse are skipped, leaving that setting at its default
+    fn parse(file: &str) -> Self {
+        let mut bindings = Self::default();
+        for line in file.lines() {
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            if name == "layout" {
+                bindings.layout = match value {
+                    "azerty" => Layout::Azerty,
+                    "dvorak" => Layout::Dvorak,
+                    _ => Layout::Qwerty,
+                };
+            } else if let ("chord_window", Some(window)) = (
+                name,
+                value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|window| window.is_finite()),
+            ) {
+                bindings.chord_window = window.clamp(0., MAX_CHORD_WINDOW);
+            } else if let (Some(action), Some((key, _, _))) = (
+                ACTIONS.iter().find(|action| action.id() == name),
+                BINDABLE.iter().find(|(_, id, _)| *id == value),
+            ) {
+                bindings.rebind(*action, *key);
+            } else {
+                log::warn!("ignoring control setting {line}");
+            }
+        }
+        bindings
+    }
+
+    fn save(&self) {
+        storage::save(SETTINGS_FILE, &self.to_text());
+    }
+
+    fn to_text(&self) -> String {
+        let layout = match self.layout {
+            Layout::Qwerty => "qwerty",
+            Layout::Azerty => "azerty",
+            Layout::Dvorak => "dvorak",
+        };
+        let mut file = format!("layout={layout}\nchord_window={:.2}\n", self.chord_window);
+        for action in ACTIONS {
+            if let Some((_, id, _)) = BINDABLE.iter().find(|(key, _, _)| *key == self.key(action)) {
+                file.push_str(&format!("{}={id}\n", action.id()));
+            }
+        }
+        file
+    }
+}
+
+/// Keybinding settings screen.
+pub struct ControlsPlugin;
+
+#[derive(Resource, Default)]
+struct ControlsScreen {
+    selected: usize,
+    waiting: bool,
+    message: String,
+}
+
+#[derive(Component)]
+struct ControlsText;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(KeyBindings::load())
+            .init_resource::<ControlsScreen>()
+            .add_systems(OnEnter(AppState::Controls), enter_controls)
+            .add_systems(OnExit(AppState::Controls), exit_controls)
+            .add_systems(
+                Update,
+                (controls_input, update_controls_text)
+                    .chain()
+                    .run_if(in_state(AppState::Controls)),
+            );
+    }
+}
+
+fn enter_controls(mut commands: Commands, mut screen: ResMut<ControlsScreen>) {
+    *screen = ControlsScreen::default();
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.),
+            top: Val::Px(20.),
+            ..default()
+        })
+        .with_background_color(Color::srgba(0., 0., 0., 0.85)),
+        ControlsText,
+    ));
+}
+
+fn exit_controls(mut commands: Commands, text: Query<Entity, With<ControlsText>>) {
+    for entity in text.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn controls_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut screen: ResMut<ControlsScreen>,
+    mut bindings: ResMut<KeyBindings>,
+    mut pad_settings: ResMut<PadSettings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let action = ACTIONS[screen.selected];
+    if screen.waiting {
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            screen.waiting = false;
+            screen.message.clear();
+            return;
+        }
+        let pressed = BINDABLE
+            .iter()
+            .find(|(key, _, _)| keyboard_input.just_pressed(*key));
+        if let Some((key, _, _)) = pressed {
+            screen.message = match bindings.rebind(action, *key) {
+                Some(other) => format!(
+                    "{} was used by {}, which now uses {}",
+                    bindings.label(action),
+                    other.name(),
+                    bindings.label(other)
+                ),
+                None => String::new(),
+            };
+            screen.waiting = false;
+            bindings.save();
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Title);
+    } else if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+        screen.selected = (screen.selected + ACTIONS.len() - 1) % ACTIONS.len();
+    } else if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        screen.selected = (screen.selected + 1) % ACTIONS.len();
+    } else if keyboard_input.just_pressed(KeyCode::Enter) {
+        screen.waiting = true;
+        screen.message = format!("Press a key for {}, Esc to cancel", action.name());
+    } else if keyboard_input.just_pressed(KeyCode::Minus) {
+        bindings.chord_window = (bindings.chord_window - CHORD_STEP).max(0.);
+        bindings.save();
+    } else if keyboard_input.just_pressed(KeyCode::Equal) {
+        bindings.chord_window = (bindings.chord_window + CHORD_STEP).min(MAX_CHORD_WINDOW);
+        bindings.save();
+    } else if keyboard_input.just_pressed(KeyCode::BracketLeft) {
+        pad_settings.nudge_deadzone(-DEADZONE_STEP);
+        pad_settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::BracketRight) {
+        pad_settings.nudge_deadzone(DEADZONE_STEP);
+        pad_settings.save();
+    } else {
+        let presets = [
+            (KeyCode::Digit1, Layout::Qwerty),
+            (KeyCode::Digit2, Layout::Azerty),
+            (KeyCode::Digit3, Layout::Dvorak),
+        ];
+        for (key, layout) in presets {
+            if keyboard_input.just_pressed(key) {
+                *bindings = KeyBindings {
+                    chord_window: bindings.chord_window,
+                    ..KeyBindings::preset(layout)
+                };
+                bindings.save();
+                screen.message = format!("Loaded {layout:?} preset");
+            }
+        }
+    }
+}
+
+fn update_controls_text(
+    screen: Res<ControlsScreen>,
+    bindings: Res<KeyBindings>,
+    pad: PadInput,
+    pad_settings: Res<PadSettings>,
+    mut text: Query<&mut Text, With<ControlsText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    let mut message = format!("Controls ({:?})\n\n", bindings.layout);
+    for (i, action) in ACTIONS.iter().enumerate() {
+        let cursor = if i == screen.selected { "> " } else { "  " };
+        message.push_str(&format!(
+            "{cursor}{}: {}\n",
+            action.name(),
+            bindings.label(*action)
+        ));
+    }
+    message.push_str(
+        "\nUp/Down arrows: select  Enter: rebind\n1: QWERTY  2: AZERTY  3: Dvorak  Esc: back\n",
+    );
+    message.push_str(&format!(
+        "Diagonal key window {:.0}ms  - / =: adjust\n",
+        bindings.chord_window * 1000.
+    ));
+    message.push_str(&format!(
+        "\nGamepad: {}\nStick or d-pad to move, knights hold a diagonal with L1 (wide) or R1 (narrow)\nStick deadzone {:.2}  [ / ]: adjust\n",
+        pad.name().unwrap_or("none connected"),
+        pad_settings.deadzone
+    ));
+    message.push_str(&screen.message);
+    if text.sections[0].value != message {
+        text.sections[0].value = message;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_survive_a_round_trip() {
+        let mut bindings = KeyBindings::preset(Layout::Azerty);
+        bindings.chord_window = 0.12;
+        bindings.rebind(Action::Up, KeyCode::KeyQ);
+        bindings.rebind(Action::UpLeftWide, KeyCode::KeyW);
+        let read = KeyBindings::parse(&bindings.to_text());
+        assert_eq!(read.layout, Layout::Azerty);
+        assert_eq!(read.chord_window, 0.12);
+        assert_eq!(read.keys, bindings.keys);
+    }
+
+    #[test]
+    fn bad_lines_keep_the_defaults() {
+        let file = "layout\nUp=NoSuchKey\nJump=KeyQ\nchord_window=NaN\nchord_window=soon\n";
+        let read = KeyBindings::parse(file);
+        assert_eq!(read.layout, Layout::Qwerty);
+        assert_eq!(read.chord_window, DEFAULT_CHORD_WINDOW);
+        assert_eq!(read.keys, DEFAULT_KEYS);
+        assert_eq!(
+            KeyBindings::parse("chord_window=9").chord_window,
+            MAX_CHORD_WINDOW
+        );
+    }
+}
@@ -0,0 +1,500 @@
+use bevy::prelude::*;
+use bevy::utils::SystemTime;
+use std::fmt;
+
+use crate::assist::AssistSettings;
+use crate::board::{Board, BoardPos, TileType};
+use crate::gamepad::PadInput;
+use crate::menu::AppState;
+use crate::pieces::{Piece, Player};
+use crate::scores::HighScores;
+use crate::spawner::Difficulty;
+use crate::{storage, GameEnded, GameOver, NewGame, RunStats, N_TILES};
+
+const SCORES_FILE: &str = "daily.txt";
+const RESULT_FILE: &str = "daily-result.txt";
+const PIECES: [Piece; 4] = [Piece::Rook, Piece::Bishop, Piece::Knight, Piece::Queen];
+// side of a tile in the result grid on screen
+const GRID_TILE: f32 = 18.;
+// the web build has no clipboard to copy the result to
+const CLIPBOARD: bool = cfg!(not(target_arch = "wasm32"));
+
+/// One game a day that is the same for everyone: the seed, player piece
+/// and difficulty all come from the UTC date.
+pub struct DailyPlugin;
+
+/// A calendar day, UTC.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    // days since 1970-01-01
+    days: i64,
+}
+
+impl Date {
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Self::from_days((secs / 86_400) as i64)
+    }
+
+    // Howard Hinnant's civil_from_days
+    fn from_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Self {
+            year,
+            month,
+            day,
+            days,
+        }
+    }
+
+    /// Mixes the day number so neighbouring days play nothing alike.
+    pub fn seed(self) -> u64 {
+        // splitmix64
+        let mut z = (self.days as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn piece(self) -> Piece {
+        PIECES[(self.seed() % PIECES.len() as u64) as usize]
+    }
+
+    /// Easy early in the week, hard at the weekend.
+    pub fn difficulty(self) -> Difficulty {
+        // 1970-01-01 was a Thursday; 0 is Monday
+        match (self.days + 3).rem_euclid(7) {
+            0 | 1 => Difficulty::Easy,
+            2..=4 => Difficulty::Normal,
+            _ => Difficulty::Hard,
+        }
+    }
+
+    pub fn new_game(self) -> NewGame {
+        NewGame {
+            piece: self.piece(),
+            difficulty: self.difficulty(),
+            seed: Some(self.seed()),
+            ..default()
+        }
+    }
+
+    /// What the day's game is, for menus.
+    pub fn describe(self) -> String {
+        format!("{self}: {:?}, {:?}", self.piece(), self.difficulty()).to_lowercase()
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Today's challenge, its own high-score table and the current run.
+#[derive(Resource)]
+pub struct Daily {
+    pub date: Date,
+    /// Only today's scores; older days are dropped.
+    pub scores: HighScores,
+    /// Place in today's table of the last run, if it made it.
+    pub rank: Option<usize>,
+    /// Seconds the player spent on each tile this run, by row then column.
+    tile_secs: [[f32; N_TILES]; N_TILES],
+    /// Where the player was taken, once the run has ended.
+    caught_at: Option<BoardPos>,
+    /// The last result, ready to paste.
+    pub share: String,
+    /// Whether copying the last result worked, once tried.
+    pub copied: Option<bool>,
+}
+
+impl Daily {
+    fn load() -> Self {
+        let date = Date::today();
+        let file = storage::load(SCORES_FILE).unwrap_or_default();
+        Self {
+            date,
+            scores: Self::parse_scores(&file, date),
+            rank: None,
+            tile_secs: default(),
+            caught_at: None,
+            share: String::new(),
+            copied: None,
+        }
+    }
+
+    // the scores file's table if it was saved on `date`, else an empty one
+    fn parse_scores(file: &str, date: Date) -> HighScores {
+        let mut lines = file.lines();
+        let today =
+            lines.next().and_then(|line| line.strip_prefix("date=")) == Some(&date.to_string());
+        if today {
+            HighScores::parse(lines)
+        } else {
+            HighScores::default()
+        }
+    }
+
+    fn scores_text(&self) -> String {
+        let mut file = vec![format!("date={}", self.date)];
+        file.extend(self.scores.to_lines());
+        file.join("\n")
+    }
+
+    fn save(&self) {
+        storage::save(SCORES_FILE, &self.scores_text());
+    }
+
+    // a tile of the result grid, as an emoji to share and a colour to show
+    fn tile_mark(&self, pos: BoardPos) -> (&'static str, Color) {
+        let most = self.tile_secs.iter().flatten().copied().fold(0., f32::max);
+        match self.tile_secs[pos.y][pos.x] {
+            _ if self.caught_at == Some(pos) => ("💥", Color::srgb(1., 0.5, 0.)),
+            0. => ("⬛", Color::srgb(0.1, 0.1, 0.1)),
+            secs if secs <= most * 0.1 => ("🟩", Color::srgb(0.3, 0.8, 0.3)),
+            secs if secs <= most * 0.5 => ("🟨", Color::srgb(0.95, 0.85, 0.2)),
+            _ => ("🟥", Color::srgb(0.9, 0.2, 0.2)),
+        }
+    }
+
+    /// Time survived and an 8x8 grid of where the player spent it.
+    fn share_text(&self, stats: &RunStats) -> String {
+        let mut text = format!(
+            "Chess Dodge daily {}\n{}: survived {:.1}s\n",
+            self.date,
+            format!("{:?}, {:?}", self.date.piece(), self.date.difficulty()).to_lowercase(),
+            stats.score(),
+        );
+        for y in 0..N_TILES {
+            for x in 0..N_TILES {
+                text += self.tile_mark(BoardPos::new(x, y)).0;
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn copy_to_clipboard(clipboard: &mut Option<arboard::Clipboard>, text: &str) -> bool {
+    if clipboard.is_none() {
+        *clipboard = arboard::Clipboard::new()
+            .map_err(|err| warn!("no clipboard: {err}"))
+            .ok();
+    }
+    let Some(clipboard) = clipboard else {
+        return false;
+    };
+    clipboard
+        .set_text(text)
+        .map_err(|err| warn!("could not copy the daily result: {err}"))
+        .is_ok()
+}
+
+#[derive(Component)]
+struct DailyUi;
+
+#[derive(Component)]
+struct DailyText;
+
+// the result grid shown once a run ends
+#[derive(Component)]
+struct ShareGrid;
+
+#[derive(Component)]
+struct ShareTile(BoardPos);
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Daily::load())
+            .add_systems(OnEnter(AppState::Daily), enter_daily)
+            .add_systems(OnExit(AppState::Daily), exit_daily)
+            .add_systems(
+                Update,
+                (
+                    track_tiles,
+                    record_daily,
+                    daily_input,
+                    update_daily_text,
+                    update_share_grid,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Daily)),
+            );
+    }
+}
+
+fn start_daily(
+    daily: &mut Daily,
+    assist: &AssistSettings,
+    new_game: &mut EventWriter<NewGame>,
+    virtual_time: &mut Time<Virtual>,
+) {
+    // a session left open past midnight moves on to the new day
+    let today = Date::today();
+    if daily.date != today {
+        daily.date = today;
+        daily.scores.scores.clear();
+    }
+    daily.rank = None;
+    daily.copied = None;
+    daily.tile_secs = default();
+    daily.caught_at = None;
+    virtual_time.set_relative_speed(assist.game_speed());
+    new_game.send(NewGame {
+        assisted: assist.any(),
+        ..daily.date.new_game()
+    });
+}
+
+fn enter_daily(
+    mut commands: Commands,
+    mut daily: ResMut<Daily>,
+    assist: Res<AssistSettings>,
+    mut new_game: EventWriter<NewGame>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    start_daily(&mut daily, &assist, &mut new_game, &mut virtual_time);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            DailyUi,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::Grid,
+                            grid_template_columns: RepeatedGridTrack::px(N_TILES as u16, GRID_TILE),
+                            grid_auto_rows: vec![GridTrack::px(GRID_TILE)],
+                            padding: UiRect::all(Val::Px(6.)),
+                            column_gap: Val::Px(2.),
+                            row_gap: Val::Px(2.),
+                            ..default()
+                        },
+                        background_color: Color::srgba(0., 0., 0., 0.7).into(),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    ShareGrid,
+                ))
+                .with_children(|grid| {
+                    for pos in BoardPos::all() {
+                        grid.spawn((NodeBundle::default(), ShareTile(pos)));
+                    }
+                });
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 32.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center)
+                .with_background_color(Color::srgba(0., 0., 0., 0.7)),
+                DailyText,
+            ));
+        });
+}
+
+fn exit_daily(mut commands: Commands, ui: Query<Entity, With<DailyUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn track_tiles(
+    time: Res<Time>,
+    game_over: Res<GameOver>,
+    mut daily: ResMut<Daily>,
+    board: Query<&Board>,
+    player: Query<Entity, With<Player>>,
+) {
+    if game_over.0 {
+        return;
+    }
+    let (Ok(board), Ok(player)) = (board.get_single(), player.get_single()) else {
+        return;
+    };
+    if let Some(pos) = board.find(TileType::Player(player)) {
+        daily.tile_secs[pos.y][pos.x] += time.delta_seconds();
+    }
+}
+
+fn record_daily(mut ended: EventReader<GameEnded>, stats: Res<RunStats>, mut daily: ResMut<Daily>) {
+    for _ in ended.read() {
+        daily.caught_at = stats.death_at;
+        if !stats.assisted {
+            daily.rank = daily.scores.submit(stats.score());
+            if daily.rank.is_some() {
+                daily.save();
+            }
+        }
+        daily.share = daily.share_text(&stats);
+        storage::save(RESULT_FILE, &daily.share);
+        info!("daily result:\n{}", daily.share);
+    }
+}
+
+fn daily_input(
+    #[cfg(not(target_arch = "wasm32"))] mut clipboard: Local<Option<arboard::Clipboard>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pad: PadInput,
+    game_over: Res<GameOver>,
+    assist: Res<AssistSettings>,
+    mut daily: ResMut<Daily>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) || pad.just_pressed(GamepadButtonType::Select) {
+        next_state.set(AppState::Title);
+    } else if game_over.0
+        && (keyboard_input.just_pressed(KeyCode::Enter)
+            || pad.just_pressed(GamepadButtonType::Start))
+    {
+        start_daily(&mut daily, &assist, &mut new_game, &mut virtual_time);
+    } else if CLIPBOARD && game_over.0 && keyboard_input.just_pressed(KeyCode::KeyC) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            daily.copied = Some(copy_to_clipboard(&mut clipboard, &daily.share));
+        }
+    }
+}
+
+fn update_daily_text(
+    daily: Res<Daily>,
+    game_over: Res<GameOver>,
+    stats: Res<RunStats>,
+    mut query: Query<(&mut Text, &mut Visibility), With<DailyText>>,
+) {
+    let Ok((mut text, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    let message = if game_over.0 {
+        let record = match (stats.assisted, daily.rank) {
+            (true, _) => "Assisted runs are not scored".to_string(),
+            (false, Some(0)) => "Best today!".to_string(),
+            (false, Some(rank)) => format!("#{} today", rank + 1),
+            (false, None) => String::new(),
+        };
+        let best = daily
+            .scores
+            .best()
+            .map_or(String::new(), |best| format!("Best today {best:.1}"));
+        let copy = match daily.copied {
+            _ if !CLIPBOARD => "",
+            None => "C: copy result  ",
+            Some(true) => "Copied!  ",
+            Some(false) => "Couldn't copy  ",
+        };
+        format!(
+            "Daily challenge {}\nSurvived {:.1}s\n{record}\n{best}\n{copy}Enter: retry  Esc: title",
+            daily.date.describe(),
+            stats.score(),
+        )
+    } else {
+        String::new()
+    };
+    *visibility = if message.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    if text.sections[0].value != message {
+        text.sections[0].value = message;
+    }
+}
+
+fn update_share_grid(
+    daily: Res<Daily>,
+    game_over: Res<GameOver>,
+    mut grid: Query<&mut Visibility, With<ShareGrid>>,
+    mut tiles: Query<(&ShareTile, &mut BackgroundColor)>,
+) {
+    let Ok(mut visibility) = grid.get_single_mut() else {
+        return;
+    };
+    *visibility = if game_over.0 {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    if game_over.0 && daily.is_changed() {
+        for (tile, mut color) in tiles.iter_mut() {
+            *color = daily.tile_mark(tile.0).1.into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daily(date: Date, scores: Vec<f32>) -> Daily {
+        Daily {
+            date,
+            scores: HighScores { scores },
+            rank: None,
+            tile_secs: default(),
+            caught_at: None,
+            share: String::new(),
+            copied: None,
+        }
+    }
+
+    #[test]
+    fn dates_count_from_1970() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(20_000).to_string(), "2024-10-04");
+        assert_eq!(Date::from_days(-1).to_string(), "1969-12-31");
+    }
+
+    #[test]
+    fn todays_scores_survive_a_round_trip() {
+        let date = Date::from_days(20_000);
+        let file = daily(date, vec![42.5, 9.25]).scores_text();
+        assert_eq!(Daily::parse_scores(&file, date).scores, vec![42.5, 9.25]);
+        // a new day starts a new table
+        let tomorrow = Date::from_days(20_001);
+        assert!(Daily::parse_scores(&file, tomorrow).scores.is_empty());
+    }
+
+    #[test]
+    fn bad_lines_are_skipped() {
+        let date = Date::from_days(20_000);
+        let file = "date=2024-10-04\n12\nlots\ninf\n-1\n30";
+        assert_eq!(Daily::parse_scores(file, date).scores, vec![30., 12.]);
+        assert!(Daily::parse_scores("12\n30", date).scores.is_empty());
+        assert!(Daily::parse_scores("date=someday\n12", date)
+            .scores
+            .is_empty());
+    }
+}
@@ -0,0 +1,519 @@
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+
+use crate::board::{Board, BoardPos};
+use crate::bots::Bot;
+use crate::level::{playable, spawnable, Goal, Level, MAX_SPAWN_SPEED};
+use crate::menu::AppState;
+use crate::pieces::Piece;
+use crate::pointer::PointerInput;
+use crate::spawner::SpawnOpponent;
+use crate::terrain::Terrain;
+use crate::{storage, GameOver, NewGame, RunStats, N_TILES, SQUARE_LEN};
+
+const LEVEL_FILE: &str = "custom-level.txt";
+// in the order Q and X cycle through them, skipping any the level file can't hold
+const PIECES: [Piece; 6] = [
+    Piece::Rook,
+    Piece::Bishop,
+    Piece::Knight,
+    Piece::Queen,
+    Piece::Pawn,
+    Piece::King,
+];
+const GOALS: [Option<Goal>; 7] = [
+    None,
+    Some(Goal::Survive(20.)),
+    Some(Goal::Survive(30.)),
+    Some(Goal::Survive(45.)),
+    Some(Goal::Survive(60.)),
+    Some(Goal::ReachTop),
+    Some(Goal::Targets(5)),
+];
+// timeline entries listed at once
+const TIMELINE_LINES: usize = 6;
+const MAX_NAME_LEN: usize = 24;
+const DEFAULT_NAME: &str = "Custom level";
+
+/// Paints terrain, the player start and a spawn timeline onto the board,
+/// play-tests the result and saves it in the level file format.
+pub struct EditorPlugin;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Brush {
+    Terrain(Terrain),
+    Start,
+}
+
+impl Brush {
+    fn name(self) -> String {
+        match self {
+            Brush::Terrain(Terrain::Teleporter(id)) => format!("teleporter {id}"),
+            Brush::Terrain(terrain) => format!("{terrain:?}").to_lowercase(),
+            Brush::Start => "start".to_string(),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Editor {
+    pub level: Level,
+    cursor: BoardPos,
+    brush: Brush,
+    // the spawn N adds in the cursor's column
+    spawn_at: f32,
+    spawn_piece: Piece,
+    spawn_speed: f32,
+    /// Playing the level instead of editing it.
+    pub testing: bool,
+    // typing a new name for the level
+    naming: bool,
+    message: String,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        let level = Level {
+            name: DEFAULT_NAME.to_string(),
+            goal: Some(Goal::Survive(30.)),
+            random_spawns: false,
+            ..Level::endless()
+        };
+        Self {
+            cursor: level.start,
+            level,
+            brush: Brush::Terrain(Terrain::Wall),
+            spawn_at: 1.,
+            spawn_piece: Piece::Rook,
+            spawn_speed: 1.,
+            testing: false,
+            naming: false,
+            message: String::new(),
+        }
+    }
+}
+
+impl Editor {
+    /// Sets a tile, returning whether anything changed.
+    fn paint(&mut self, pos: BoardPos, brush: Brush) -> bool {
+        let level = &mut self.level;
+        let old = level.terrain.iter().position(|(at, _)| *at == pos);
+        match brush {
+            Brush::Start => {
+                if level.start == pos {
+                    return false;
+                }
+                // the start is always on floor
+                if let Some(old) = old {
+                    level.terrain.remove(old);
+                }
+                level.start = pos;
+            }
+            // paint around the start rather than over it
+            Brush::Terrain(_) if level.start == pos => return false,
+            Brush::Terrain(terrain) => {
+                if old.map(|old| level.terrain[old].1) == Some(terrain) {
+                    return false;
+                }
+                match old {
+                    Some(old) if terrain == Terrain::Floor => {
+                        level.terrain.remove(old);
+                    }
+                    Some(old) => level.terrain[old].1 = terrain,
+                    None if terrain == Terrain::Floor => return false,
+                    None => level.terrain.push((pos, terrain)),
+                }
+            }
+        }
+        true
+    }
+
+    /// The level as it would be read back from its file, or why it can't be.
+    fn checked(&self) -> Result<Level, String> {
+        // line numbers mean nothing here, the text was never typed
+        Level::parse(&self.level.to_text()).map_err(|err| err.kind.to_string())
+    }
+}
+
+fn next_in<T: Copy + PartialEq>(items: &[T], current: T) -> T {
+    let index = items
+        .iter()
+        .position(|item| *item == current)
+        .map_or(0, |index| index + 1);
+    items[index % items.len()]
+}
+
+fn pieces(allowed: fn(Piece) -> bool) -> Vec<Piece> {
+    PIECES.into_iter().filter(|piece| allowed(*piece)).collect()
+}
+
+// edits the name with typed characters until Enter, Tab or Escape
+fn type_name(editor: &mut Editor, typed: &mut EventReader<KeyboardInput>) {
+    for event in typed.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Enter | Key::Tab | Key::Escape => {
+                editor.naming = false;
+                let name = editor.level.name.trim().to_string();
+                editor.level.name = if name.is_empty() {
+                    DEFAULT_NAME.to_string()
+                } else {
+                    name
+                };
+                // keys after the one that finished are left for editing
+                break;
+            }
+            Key::Backspace => {
+                editor.level.name.pop();
+            }
+            Key::Space if editor.level.name.chars().count() < MAX_NAME_LEN => {
+                editor.level.name.push(' ');
+            }
+            Key::Character(chars) => {
+                for c in chars.chars().filter(|c| !c.is_control()) {
+                    if editor.level.name.chars().count() < MAX_NAME_LEN {
+                        editor.level.name.push(c);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[derive(Component)]
+struct EditorUi;
+
+#[derive(Component)]
+struct EditorText;
+
+#[derive(Component)]
+struct EditorCursor;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Editor>()
+            .add_systems(OnEnter(AppState::Editor), enter_editor)
+            .add_systems(OnExit(AppState::Editor), exit_editor)
+            .add_systems(
+                Update,
+                (editor_input, update_editor_ui)
+                    .chain()
+                    .run_if(in_state(AppState::Editor)),
+            );
+    }
+}
+
+/// Draws the level without running it: time stays paused and a bot
+/// holds the player so no move hints appear.
+fn show_level(level: &Level, new_game: &mut EventWriter<NewGame>) {
+    new_game.send(NewGame {
+        bot: Some(Bot::Scripted),
+        goal: None,
+        timeline: Vec::new(),
+        random_spawns: false,
+        ..level.new_game()
+    });
+}
+
+fn enter_editor(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    // pick up where the last session saved
+    if let Some(level) = storage::load(LEVEL_FILE).and_then(|file| Level::parse(&file).ok()) {
+        editor.cursor = level.start;
+        editor.level = level;
+    }
+    editor.testing = false;
+    editor.message.clear();
+    virtual_time.pause();
+    show_level(&editor.level, &mut new_game);
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::srgba(1., 0.85, 0.2, 0.4),
+                custom_size: Some(Vec2::splat(SQUARE_LEN)),
+                ..default()
+            },
+            ..default()
+        },
+        EditorCursor,
+        EditorUi,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(5.),
+            top: Val::Px(5.),
+            ..default()
+        })
+        .with_background_color(Color::srgba(0., 0., 0., 0.7)),
+        EditorText,
+        EditorUi,
+    ));
+}
+
+fn exit_editor(
+    mut commands: Commands,
+    ui: Query<Entity, With<EditorUi>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    virtual_time.unpause();
+}
+
+fn editor_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    pointer: Res<PointerInput>,
+    game_over: Res<GameOver>,
+    mut editor: ResMut<Editor>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut new_game: EventWriter<NewGame>,
+    mut typed: EventReader<KeyboardInput>,
+) {
+    let pressed = |key| keyboard_input.just_pressed(key);
+    if editor.naming {
+        type_name(&mut editor, &mut typed);
+        return;
+    }
+    typed.clear();
+    if editor.testing {
+        if pressed(KeyCode::Escape) || (game_over.0 && pressed(KeyCode::Enter)) {
+            editor.testing = false;
+            virtual_time.pause();
+            show_level(&editor.level, &mut new_game);
+        }
+        return;
+    }
+    if pressed(KeyCode::Escape) {
+        next_state.set(AppState::Title);
+        return;
+    }
+    if pressed(KeyCode::Tab) {
+        editor.naming = true;
+        editor.message.clear();
+        return;
+    }
+    if pressed(KeyCode::Enter) {
+        match editor.checked() {
+            Ok(level) => {
+                editor.testing = true;
+                editor.message.clear();
+                virtual_time.unpause();
+                virtual_time.set_relative_speed(1.);
+                new_game.send(level.new_game());
+            }
+            Err(err) => editor.message = err,
+        }
+        return;
+    }
+    if pressed(KeyCode::KeyS) {
+        editor.message = match editor.checked() {
+            Ok(level) => {
+                storage::save(LEVEL_FILE, &level.to_text());
+                format!("Saved {LEVEL_FILE}")
+            }
+            Err(err) => err,
+        };
+    }
+
+    let cursor = editor.cursor;
+    let step = |delta: isize, at: usize| at.saturating_add_signed(delta).min(N_TILES - 1);
+    if pressed(KeyCode::ArrowUp) {
+        editor.cursor.y = step(-1, cursor.y);
+    } else if pressed(KeyCode::ArrowDown) {
+        editor.cursor.y = step(1, cursor.y);
+    } else if pressed(KeyCode::ArrowLeft) {
+        editor.cursor.x = step(-1, cursor.x);
+    } else if pressed(KeyCode::ArrowRight) {
+        editor.cursor.x = step(1, cursor.x);
+    }
+
+    let brushes = [
+        (KeyCode::Digit1, Brush::Terrain(Terrain::Floor)),
+        (KeyCode::Digit2, Brush::Terrain(Terrain::Wall)),
+        (KeyCode::Digit3, Brush::Terrain(Terrain::Hole)),
+        (KeyCode::Digit4, Brush::Terrain(Terrain::Ice)),
+        (KeyCode::Digit5, Brush::Terrain(Terrain::Teleporter(0))),
+        (KeyCode::Digit6, Brush::Start),
+    ];
+    for (key, brush) in brushes {
+        if pressed(key) {
+            editor.brush = match (editor.brush, brush) {
+                // pressing it again picks the next pair
+                (
+                    Brush::Terrain(Terrain::Teleporter(id)),
+                    Brush::Terrain(Terrain::Teleporter(_)),
+                ) => Brush::Terrain(Terrain::Teleporter((id + 1) % 10)),
+                _ => brush,
+            };
+        }
+    }
+
+    let mut changed = false;
+    if pressed(KeyCode::Space) {
+        let (cursor, brush) = (editor.cursor, editor.brush);
+        changed |= editor.paint(cursor, brush);
+    }
+    if let Some(hovered) = pointer.hovered {
+        if mouse.pressed(MouseButton::Left) {
+            editor.cursor = hovered;
+            let brush = editor.brush;
+            changed |= editor.paint(hovered, brush);
+        } else if mouse.pressed(MouseButton::Right) {
+            editor.cursor = hovered;
+            changed |= editor.paint(hovered, Brush::Terrain(Terrain::Floor));
+        }
+    }
+    if pressed(KeyCode::KeyQ) {
+        editor.level.piece = next_in(&pieces(playable), editor.level.piece);
+        changed = true;
+    }
+    if pressed(KeyCode::KeyG) {
+        editor.level.goal = next_in(&GOALS, editor.level.goal);
+    }
+    if pressed(KeyCode::KeyR) {
+        editor.level.random_spawns = !editor.level.random_spawns;
+    }
+
+    if pressed(KeyCode::KeyX) {
+        editor.spawn_piece = next_in(&pieces(spawnable), editor.spawn_piece);
+    }
+    // whole tenths and halves keep the saved numbers short
+    if pressed(KeyCode::Minus) {
+        editor.spawn_at = (editor.spawn_at - 0.5).max(0.);
+    } else if pressed(KeyCode::Equal) {
+        editor.spawn_at += 0.5;
+    }
+    if pressed(KeyCode::BracketLeft) {
+        editor.spawn_speed = ((editor.spawn_speed * 10.).round() - 1.).max(1.) / 10.;
+    } else if pressed(KeyCode::BracketRight) {
+        editor.spawn_speed = (((editor.spawn_speed * 10.).round() + 1.) / 10.).min(MAX_SPAWN_SPEED);
+    }
+    if pressed(KeyCode::KeyN) {
+        let entry = (
+            editor.spawn_at,
+            SpawnOpponent {
+                col: editor.cursor.x,
+                piece: editor.spawn_piece,
+                speed: editor.spawn_speed,
+            },
+        );
+        // kept in the order added so Backspace undoes the latest;
+        // `checked` sorts it by time
+        editor.level.timeline.push(entry);
+        // ready for the next one a moment later
+        editor.spawn_at += 1.;
+    } else if pressed(KeyCode::Backspace) {
+        editor.level.timeline.pop();
+    }
+
+    if changed {
+        editor.message.clear();
+        show_level(&editor.level, &mut new_game);
+    }
+}
+
+fn update_editor_ui(
+    editor: Res<Editor>,
+    game_over: Res<GameOver>,
+    stats: Res<RunStats>,
+    mut cursor: Query<(&mut Transform, &mut Visibility), With<EditorCursor>>,
+    mut text: Query<&mut Text, With<EditorText>>,
+) {
+    if let Ok((mut transform, mut visibility)) = cursor.get_single_mut() {
+        let mut translation = Board::coord_to_vec(editor.cursor);
+        // over the terrain, under the pieces
+        translation.z = 0.4;
+        transform.translation = translation;
+        *visibility = if editor.testing {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    let piece_name = |piece: Piece| format!("{piece:?}").to_lowercase();
+    let level = &editor.level;
+    let message = if editor.testing {
+        match (game_over.0, stats.won) {
+            (false, _) => "Play-testing  Esc: back to editing".to_string(),
+            (true, true) => "Level beaten!\nEnter or Esc: back to editing".to_string(),
+            (true, false) => format!(
+                "Caught after {:.1}s\nEnter or Esc: back to editing",
+                stats.elapsed
+            ),
+        }
+    } else {
+        let goal = level
+            .goal
+            .map_or("none".to_string(), |goal| goal.to_string());
+        let spawns = if level.random_spawns {
+            "random + timeline"
+        } else {
+            "timeline only"
+        };
+        let mut lines = vec![
+            if editor.naming {
+                format!("Level name: {}_  Enter: done", level.name)
+            } else {
+                format!("Level editor: {}  Tab: rename", level.name)
+            },
+            format!(
+                "Brush [{}]  1 floor 2 wall 3 hole 4 ice 5 teleporter 6 start",
+                editor.brush.name()
+            ),
+            "Space or click: paint  Right click: erase  Arrows: move".to_string(),
+            format!(
+                "Q: piece [{}]  G: goal [{goal}]  R: spawns [{spawns}]",
+                piece_name(level.piece)
+            ),
+            format!(
+                "N: spawn {} in column {} at {:.1}s, {:.1}s per move",
+                piece_name(editor.spawn_piece),
+                editor.cursor.x,
+                editor.spawn_at,
+                editor.spawn_speed,
+            ),
+            "X: spawn piece  -/=: time  [/]: speed  Backspace: undo last added".to_string(),
+            format!("Timeline ({} spawns)", level.timeline.len()),
+        ];
+        let skip = level.timeline.len().saturating_sub(TIMELINE_LINES);
+        for (at, spawn) in level.timeline.iter().skip(skip) {
+            lines.push(format!(
+                "  {at:.1}s: {} in column {}, {:.1}s per move",
+                piece_name(spawn.piece),
+                spawn.col,
+                spawn.speed
+            ));
+        }
+        lines.push("Enter: play-test  S: save  Esc: title".to_string());
+        if !editor.message.is_empty() {
+            lines.push(editor.message.clone());
+        }
+        lines.join("\n")
+    };
+    if text.sections[0].value != message {
+        text.sections[0].value = message;
+    }
+}
@@ -0,0 +1,167 @@
+use bevy::ecs::system::SystemParam;
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::prelude::*;
+
+use crate::controls::Action;
+use crate::storage;
+
+const SETTINGS_FILE: &str = "gamepad.txt";
+pub const DEFAULT_DEADZONE: f32 = 0.35;
+pub const MIN_DEADZONE: f32 = 0.05;
+pub const MAX_DEADZONE: f32 = 0.9;
+
+/// Gamepad options shown on the controls screen.
+#[derive(Resource)]
+pub struct PadSettings {
+    /// Stick deflection below which the stick counts as centred.
+    pub deadzone: f32,
+}
+
+/// The gamepad that controls the player, following hot-plugging.
+#[derive(Resource, Default)]
+pub struct ActiveGamepad(pub Option<Gamepad>);
+
+/// Tracks which gamepad is in use as pads connect and disconnect.
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PadSettings::load())
+            .init_resource::<ActiveGamepad>()
+            .add_systems(PreUpdate, track_gamepads.after(bevy::input::InputSystem));
+    }
+}
+
+impl PadSettings {
+    fn load() -> Self {
+        let deadzone = storage::load(SETTINGS_FILE)
+            .and_then(|file| {
+                file.lines()
+                    .filter_map(|line| line.split_once('='))
+                    .find(|(name, _)| name.trim() == "deadzone")
+                    .and_then(|(_, value)| value.trim().parse().ok())
+            })
+            .unwrap_or(DEFAULT_DEADZONE);
+        Self {
+            deadzone: f32::clamp(deadzone, MIN_DEADZONE, MAX_DEADZONE),
+        }
+    }
+
+    pub fn save(&self) {
+        storage::save(SETTINGS_FILE, &format!("deadzone={:.2}\n", self.deadzone));
+    }
+
+    pub fn nudge_deadzone(&mut self, by: f32) {
+        self.deadzone = (self.deadzone + by).clamp(MIN_DEADZONE, MAX_DEADZONE);
+    }
+}
+
+fn track_gamepads(
+    mut connections: EventReader<GamepadConnectionEvent>,
+    gamepads: Res<Gamepads>,
+    mut active: ResMut<ActiveGamepad>,
+) {
+    for event in connections.read() {
+        match &event.connection {
+            GamepadConnection::Connected(info) => {
+                log::info!("gamepad {} connected: {}", event.gamepad.id, info.name);
+                if active.0.is_none() {
+                    active.0 = Some(event.gamepad);
+                }
+            }
+            GamepadConnection::Disconnected => {
+                log::info!("gamepad {} disconnected", event.gamepad.id);
+                if active.0 == Some(event.gamepad) {
+                    active.0 = gamepads.iter().find(|gamepad| *gamepad != event.gamepad);
+                }
+            }
+        }
+    }
+}
+
+/// Reads the active gamepad as player actions. The left stick or d-pad
+/// gives one of eight directions: rooks use straight ones and bishops
+/// diagonal ones. Knights hold a diagonal and pick the wide jump with
+/// the left shoulder or the narrow jump with the right shoulder.
+#[derive(SystemParam)]
+pub struct PadInput<'w> {
+    active: Res<'w, ActiveGamepad>,
+    settings: Res<'w, PadSettings>,
+    gamepads: Res<'w, Gamepads>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    buttons: Res<'w, ButtonInput<GamepadButton>>,
+}
+
+impl PadInput<'_> {
+    pub fn pressed(&self, action: Action) -> bool {
+        let Some(gamepad) = self.active.0 else {
+            return false;
+        };
+        let (x, y) = self.direction(gamepad);
+        let wide = self
+            .buttons
+            .pressed(GamepadButton::new(gamepad, GamepadButtonType::LeftTrigger));
+        let narrow = self
+            .buttons
+            .pressed(GamepadButton::new(gamepad, GamepadButtonType::RightTrigger));
+        match action {
+            Action::Up => y < 0,
+            Action::Down => y > 0,
+            Action::Left => x < 0,
+            Action::Right => x > 0,
+            Action::UpLeftWide => (x, y) == (-1, -1) && wide,
+            Action::UpLeftNarrow => (x, y) == (-1, -1) && narrow,
+            Action::UpRightNarrow => (x, y) == (1, -1) && narrow,
+            Action::UpRightWide => (x, y) == (1, -1) && wide,
+            Action::DownLeftWide => (x, y) == (-1, 1) && wide,
+            Action::DownLeftNarrow => (x, y) == (-1, 1) && narrow,
+            Action::DownRightNarrow => (x, y) == (1, 1) && narrow,
+            Action::DownRightWide => (x, y) == (1, 1) && wide,
+        }
+    }
+
+    pub fn just_pressed(&self, button: GamepadButtonType) -> bool {
+        self.active.0.is_some_and(|gamepad| {
+            self.buttons
+                .just_pressed(GamepadButton::new(gamepad, button))
+        })
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.active
+            .0
+            .and_then(|gamepad| self.gamepads.name(gamepad))
+    }
+
+    // one of eight directions as (x, y) steps on the board, y down
+    fn direction(&self, gamepad: Gamepad) -> (i32, i32) {
+        let button = |button| self.buttons.pressed(GamepadButton::new(gamepad, button)) as i32;
+        let dpad = (
+            button(GamepadButtonType::DPadRight) - button(GamepadButtonType::DPadLeft),
+            button(GamepadButtonType::DPadDown) - button(GamepadButtonType::DPadUp),
+        );
+        if dpad != (0, 0) {
+            return dpad;
+        }
+        let axis = |axis| self.axes.get(GamepadAxis::new(gamepad, axis)).unwrap_or(0.);
+        let stick = Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        if stick.length() < self.settings.deadzone {
+            return (0, 0);
+        }
+        // snap to the nearest of eight 45 degree sectors
+        let sector = (stick.y.atan2(stick.x) / std::f32::consts::FRAC_PI_4).round() as i32;
+        match sector.rem_euclid(8) {
+            0 => (1, 0),
+            1 => (1, -1),
+            2 => (0, -1),
+            3 => (-1, -1),
+            4 => (-1, 0),
+            5 => (-1, 1),
+            6 => (0, 1),
+            _ => (1, 1),
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use bevy::prelude::*;
+use nanorand::Rng;
+
+use crate::assist::AssistSettings;
+use crate::board::{Board, BoardPos, Edge, TileType};
+use crate::gamepad::PadInput;
+use crate::menu::AppState;
+use crate::pieces::Piece;
+use crate::spawner::Spawner;
+use crate::terrain::{spawn_terrain, Terrain};
+use crate::{storage, GameEnded, GameOver, GameRng, NewGame, RunStats, N_TILES};
+
+const RECORDS_FILE: &str = "gauntlet.txt";
+/// Seconds each tier lasts. The first is the usual ramp, which has
+/// plateaued well before it ends.
+pub const TIER_SECS: f32 = 30.;
+const BANNER_SECS: f32 = 3.;
+// per overdrive tier, taken off the fastest spawns and opponent moves
+const OVERDRIVE_BOOST: (f32, f32) = (0.1, 0.05);
+const MAX_BOOST: (f32, f32) = (0.4, 0.25);
+// obstacles dropped on the board by the rubble tier
+const RUBBLE: [Terrain; 4] = [Terrain::Wall, Terrain::Wall, Terrain::Hole, Terrain::Hole];
+
+// name and what changes, for the banner
+const TIERS: [(&str, &str); 5] = [
+    ("Warm-up", "The usual ramp"),
+    ("Knights", "Knights join in"),
+    ("Flanks", "Opponents come in from the sides"),
+    ("Rubble", "Walls and holes appear"),
+    ("Crossfire", "Opponents come in from every edge"),
+];
+
+/// Endless game that moves up a tier every `TIER_SECS`, each one
+/// adding something new, with records kept per tier.
+pub struct GauntletPlugin;
+
+/// Put on the board entity to run the game as a gauntlet.
+#[derive(Component, Default)]
+pub struct Gauntlet {
+    /// Counting from 0.
+    pub tier: usize,
+}
+
+/// Sent when a gauntlet moves up a tier.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TierReached(pub usize);
+
+pub fn tier_name(tier: usize) -> String {
+    match TIERS.get(tier) {
+        Some((name, _)) => name.to_string(),
+        None => format!("Overdrive {}", tier + 1 - TIERS.len()),
+    }
+}
+
+fn tier_description(tier: usize) -> &'static str {
+    TIERS
+        .get(tier)
+        .map_or("Everything gets faster", |(_, description)| description)
+}
+
+/// Steps the spawner and board up to the tier the game's time has reached.
+pub fn advance_gauntlet(
+    mut commands: Commands,
+    game_over: Res<GameOver>,
+    stats: Res<RunStats>,
+    mut rng: ResMut<GameRng>,
+    mut boards: Query<(&mut Board, &mut Spawner, &mut Gauntlet)>,
+    mut reached: EventWriter<TierReached>,
+) {
+    let Ok((mut board, mut spawner, mut gauntlet)) = boards.get_single_mut() else {
+        return;
+    };
+    if game_over.0 {
+        return;
+    }
+    while gauntlet.tier < (stats.elapsed / TIER_SECS) as usize {
+        gauntlet.tier += 1;
+        match gauntlet.tier {
+            1 => {
+                spawner.mix = [
+                    (Piece::Queen, 1),
+                    (Piece::Bishop, 4),
+                    (Piece::Rook, 9),
+                    (Piece::Knight, 3),
+                ]
+            }
+            2 => spawner.edges = vec![Edge::Top, Edge::Left, Edge::Right],
+            3 => drop_rubble(&mut commands, &mut board, &mut rng),
+            4 => spawner.edges = vec![Edge::Top, Edge::Bottom, Edge::Left, Edge::Right],
+            _ => overdrive(&mut spawner),
+        }
+        reached.send(TierReached(gauntlet.tier));
+    }
+}
+
+fn overdrive(spawner: &mut Spawner) {
+    spawner.boost.0 = (spawner.boost.0 + OVERDRIVE_BOOST.0).min(MAX_BOOST.0);
+    spawner.boost.1 = (spawner.boost.1 + OVERDRIVE_BOOST.1).min(MAX_BOOST.1);
+}
+
+// on empty tiles away from the edges, so every edge can still be spawned on
+fn drop_rubble(commands: &mut Commands, board: &mut Board, rng: &mut GameRng) {
+    let mut free: Vec<BoardPos> = BoardPos::all()
+        .filter(|pos| (1..N_TILES - 1).contains(&pos.x) && (1..N_TILES - 1).contains(&pos.y))
+        .filter(|pos| board.tile(*pos) == TileType::Empty && board.terrain(*pos) == Terrain::Floor)
+        .collect();
+    rng.0.shuffle(&mut free);
+    let rubble: Vec<(BoardPos, Terrain)> = free.into_iter().zip(RUBBLE).collect();
+    spawn_terrain(commands, board, &rubble);
+}
+
+/// The longest anyone has lasted in each tier, `TIER_SECS` once cleared.
+#[derive(Resource, Default)]
+pub struct GauntletRecords {
+    pub best: Vec<f32>,
+    /// Whether the last run set a record in the tier it ended in.
+    pub new_record: bool,
+}
+
+impl GauntletRecords {
+    fn load() -> Self {
+        let mut records = Self::default();
+        for (tier, secs) in storage::load(RECORDS_FILE)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .filter_map(|(tier, secs)| {
+                Some((
+                    tier.trim().parse::<usize>().ok()?,
+                    secs.trim().parse::<f32>().ok()?,
+                ))
+            })
+        {
+            if records.best.len() <= tier {
+                records.best.resize(tier + 1, 0.);
+            }
+            records.best[tier] = secs;
+        }
+        records
+    }
+
+    fn save(&self) {
+        let lines: Vec<String> = self
+            .best
+            .iter()
+            .enumerate()
+            .map(|(tier, secs)| format!("{tier}={secs:.3}"))
+            .collect();
+        storage::save(RECORDS_FILE, &lines.join("\n"));
+    }
+
+    fn submit(&mut self, elapsed: f32) {
+        let tier = (elapsed / TIER_SECS) as usize;
+        if self.best.len() <= tier {
+            self.best.resize(tier + 1, 0.);
+        }
+        for cleared in &mut self.best[..tier] {
+            *cleared = TIER_SECS;
+        }
+        let secs = elapsed - tier as f32 * TIER_SECS;
+        self.new_record = secs > self.best[tier];
+        if self.new_record {
+            self.best[tier] = secs;
+        }
+        self.save();
+    }
+}
+
+#[derive(Component)]
+struct GauntletUi;
+
+#[derive(Component)]
+struct GauntletText;
+
+#[derive(Component)]
+struct TierBanner;
+
+impl Plugin for GauntletPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GauntletRecords::load())
+            .add_systems(OnEnter(AppState::Gauntlet), enter_gauntlet)
+            .add_systems(OnExit(AppState::Gauntlet), exit_gauntlet)
+            .add_systems(
+                Update,
+                (
+                    record_gauntlet,
+                    gauntlet_input,
+                    show_banner,
+                    update_gauntlet_text,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Gauntlet)),
+            );
+    }
+}
+
+fn start_gauntlet(
+    assist: &AssistSettings,
+    new_game: &mut EventWriter<NewGame>,
+    virtual_time: &mut Time<Virtual>,
+    reached: &mut EventWriter<TierReached>,
+) {
+    virtual_time.set_relative_speed(assist.game_speed());
+    new_game.send(NewGame {
+        assisted: assist.any(),
+        gauntlet: true,
+        ..default()
+    });
+    // the banner announces the first tier too
+    reached.send(TierReached(0));
+}
+
+fn enter_gauntlet(
+    mut commands: Commands,
+    assist: Res<AssistSettings>,
+    mut new_game: EventWriter<NewGame>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut reached: EventWriter<TierReached>,
+) {
+    start_gauntlet(&assist, &mut new_game, &mut virtual_time, &mut reached);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            GauntletUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 32.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center)
+                .with_background_color(Color::srgba(0., 0., 0., 0.7)),
+                GauntletText,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 36.,
+                        color: Color::srgb(1., 0.8, 0.2),
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center)
+                .with_background_color(Color::srgba(0., 0., 0., 0.7))
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(40.),
+                    ..default()
+                }),
+                TierBanner,
+            ));
+        });
+}
+
+fn exit_gauntlet(mut commands: Commands, ui: Query<Entity, With<GauntletUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn record_gauntlet(
+    mut ended: EventReader<GameEnded>,
+    stats: Res<RunStats>,
+    mut records: ResMut<GauntletRecords>,
+) {
+    for _ in ended.read() {
+        records.new_record = false;
+        if !stats.assisted {
+            records.submit(stats.elapsed);
+        }
+    }
+}
+
+fn gauntlet_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pad: PadInput,
+    game_over: Res<GameOver>,
+    assist: Res<AssistSettings>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut new_game: EventWriter<NewGame>,
+    mut reached: EventWriter<TierReached>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) || pad.just_pressed(GamepadButtonType::Select) {
+        next_state.set(AppState::Title);
+    } else if game_over.0
+        && (keyboard_input.just_pressed(KeyCode::Enter)
+            || pad.just_pressed(GamepadButtonType::Start))
+    {
+        start_gauntlet(&assist, &mut new_game, &mut virtual_time, &mut reached);
+    }
+}
+
+fn show_banner(
+    time: Res<Time>,
+    game_over: Res<GameOver>,
+    mut reached: EventReader<TierReached>,
+    mut query: Query<(&mut Text, &mut Visibility), With<TierBanner>>,
+    mut shown_for: Local<f32>,
+) {
+    let Ok((mut text, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    if let Some(TierReached(tier)) = reached.read().last() {
+        text.sections[0].value = format!(
+            "Tier {}: {}\n{}",
+            tier + 1,
+            tier_name(*tier),
+            tier_description(*tier)
+        );
+        *shown_for = 0.;
+    }
+    *shown_for += time.delta_seconds();
+    *visibility = if *shown_for < BANNER_SECS && !game_over.0 && !text.sections[0].value.is_empty()
+    {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+}
+
+fn update_gauntlet_text(
+    records: Res<GauntletRecords>,
+    game_over: Res<GameOver>,
+    stats: Res<RunStats>,
+    mut query: Query<(&mut Text, &mut Visibility), With<GauntletText>>,
+) {
+    let Ok((mut text, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    let message = if game_over.0 {
+        let tier = (stats.elapsed / TIER_SECS) as usize;
+        let mut lines = vec![format!(
+            "Caught in tier {} ({}) after {:.1}s",
+            tier + 1,
+            tier_name(tier),
+            stats.elapsed
+        )];
+        if stats.assisted {
+            lines.push("Assisted runs are not scored".to_string());
+        } else if records.new_record {
+            lines.push(format!("New record for {}!", tier_name(tier)));
+        }
+        lines.push("\nTier records".to_string());
+        // the tiers reached so far and the next one to aim for
+        for shown in 0..=records.best.len().max(tier + 1) {
+            let best = match records.best.get(shown) {
+                Some(secs) if *secs >= TIER_SECS => "cleared".to_string(),
+                Some(secs) if *secs > 0. => format!("{secs:.1}s of {TIER_SECS:.0}"),
+                _ => "-".to_string(),
+            };
+            lines.push(format!("{}. {}: {best}", shown + 1, tier_name(shown)));
+        }
+        lines.push("Enter: retry  Esc: title".to_string());
+        lines.join("\n")
+    } else {
+        String::new()
+    };
+    *visibility = if message.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    if text.sections[0].value != message {
+        text.sections[0].value = message;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawner::{Difficulty, MIN_TIMER_SECS};
+    use crate::{OPP_SPEED_DECR, SPAWN_DUR_DECR};
+
+    #[test]
+    fn overdrive_bottoms_out_at_the_timer_floor() {
+        for difficulty in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard] {
+            let mut spawner = Spawner::new(true, difficulty);
+            // well past the tier where the boost stops growing
+            for tier in 1..TIERS.len() + 20 {
+                if tier >= TIERS.len() {
+                    overdrive(&mut spawner);
+                }
+                for _ in 0..50 {
+                    spawner.step_ramp();
+                    assert!(
+                        spawner.spawn_secs() >= MIN_TIMER_SECS,
+                        "{difficulty:?} tier {tier}"
+                    );
+                    assert!(
+                        spawner.move_secs() >= MIN_TIMER_SECS,
+                        "{difficulty:?} tier {tier}"
+                    );
+                }
+            }
+            assert_eq!(spawner.boost, MAX_BOOST);
+            // the ramp has stopped rather than drifting on below the floor
+            let reached = (spawner.cur_duration, spawner.cur_piece_speed);
+            for _ in 0..1000 {
+                spawner.step_ramp();
+            }
+            assert_eq!((spawner.cur_duration, spawner.cur_piece_speed), reached);
+            assert!(
+                reached.0 > MIN_TIMER_SECS - SPAWN_DUR_DECR,
+                "{difficulty:?}"
+            );
+            assert!(
+                reached.1 > MIN_TIMER_SECS - OPP_SPEED_DECR,
+                "{difficulty:?}"
+            );
+        }
+        // hard's fastest moves are pushed down to the floor itself
+        let mut spawner = Spawner::new(true, Difficulty::Hard);
+        spawner.boost = MAX_BOOST;
+        for _ in 0..1000 {
+            spawner.step_ramp();
+        }
+        assert_eq!(spawner.move_secs(), MIN_TIMER_SECS);
+    }
+}
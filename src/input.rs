@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+
+use crate::board::{Board, Direction, MoveReq, TileType};
+use crate::bots::Bot;
+use crate::controls::{Action, KeyBindings};
+use crate::gamepad::PadInput;
+use crate::pieces::{Piece, Player};
+use crate::pointer::PointerInput;
+use crate::powerups::Dashing;
+
+// seconds a move pressed during the move cooldown is kept for
+const MOVE_BUFFER: f32 = 0.15;
+const DIRECTIONS: [Action; 4] = [Action::Up, Action::Down, Action::Left, Action::Right];
+
+/// Recent input, so quick key taps aren't lost between fixed ticks.
+pub struct InputBuffer {
+    // seconds since each of `DIRECTIONS` was last held
+    since_held: [f32; 4],
+    // move pressed during the cooldown and its age
+    buffered: Option<(Direction, f32)>,
+    // keyboard move on the previous tick, so holding a key isn't buffered
+    last: Option<Direction>,
+}
+
+impl Default for InputBuffer {
+    fn default() -> Self {
+        Self {
+            since_held: [f32::INFINITY; 4],
+            buffered: None,
+            last: None,
+        }
+    }
+}
+
+impl InputBuffer {
+    fn tick(&mut self, delta: f32, held: impl Fn(Action) -> bool) {
+        for (since, action) in self.since_held.iter_mut().zip(DIRECTIONS) {
+            *since = if held(action) { 0. } else { *since + delta };
+        }
+        if let Some((_, age)) = &mut self.buffered {
+            *age += delta;
+        }
+        self.buffered = self.buffered.filter(|(_, age)| *age <= MOVE_BUFFER);
+    }
+
+    fn held_within(&self, action: Action, window: f32) -> bool {
+        DIRECTIONS
+            .iter()
+            .position(|direction| *direction == action)
+            .is_some_and(|i| self.since_held[i] <= window)
+    }
+
+    // a used chord shouldn't combine with the next key press
+    fn moved(&mut self) {
+        self.since_held = [f32::INFINITY; 4];
+        self.buffered = None;
+    }
+}
+
+pub fn player_input(
+    time: Res<Time>,
+    mut query: Query<(&mut Player, Entity, &Piece, Option<&mut Dashing>), Without<Bot>>,
+    mut move_req_writer: EventWriter<MoveReq>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    pad: PadInput,
+    mut pointer: ResMut<PointerInput>,
+    mut buffer: Local<InputBuffer>,
+    board: Query<&Board>,
+) {
+    let mut update_sent = false;
+    let Ok((mut player, entity, piece, mut dashing)) = query.get_single_mut() else {
+        return;
+    };
+    let kp = |action| keyboard_input.pressed(bindings.key(action)) || pad.pressed(action);
+    buffer.tick(time.delta_seconds(), kp);
+    // diagonals accept their two keys pressed up to `chord_window` apart
+    let chord = |action| kp(action) || buffer.held_within(action, bindings.chord_window);
+    let key_mov = match piece {
+        Piece::Pawn => pawn_move(kp),
+        Piece::Rook => rook_move(kp),
+        Piece::Bishop => bishop_move(chord),
+        Piece::Knight => knight_move(kp),
+        // two keys make a diagonal, one a straight move
+        Piece::Queen => bishop_move(chord).or_else(|| rook_move(kp)),
+        _ => None,
+    };
+    let ready = player.ready(time.delta());
+    // a dash's second step goes out before any new move
+    if let Some(dir) = dashing.as_mut().and_then(|dash| dash.follow_up.take()) {
+        move_req_writer.send(MoveReq {
+            id: TileType::Player(entity),
+            mov: dir,
+        });
+        update_sent = true;
+    } else if ready {
+        let tap_mov = board.get_single().ok().and_then(|board| {
+            let pos = board.find(TileType::Player(entity))?;
+            pointer.take_move(board, *piece, pos)
+        });
+        let mov = key_mov.or(buffer.buffered.map(|(dir, _)| dir)).or(tap_mov);
+        if let Some(dir) = mov {
+            move_req_writer.send(MoveReq {
+                id: TileType::Player(entity),
+                mov: dir,
+            });
+            update_sent = true;
+            player.moved();
+            buffer.moved();
+            if let Some(dash) = &mut dashing {
+                dash.follow_up = Some(dir);
+            }
+        }
+    } else if let (Some(dir), true) = (key_mov, key_mov != buffer.last) {
+        buffer.buffered = Some((dir, 0.));
+    }
+    buffer.last = key_mov;
+    if !update_sent {
+        move_req_writer.send(MoveReq {
+            id: TileType::Player(entity),
+            mov: Direction::None,
+        });
+    }
+}
+
+pub fn pawn_move(kp: impl Fn(Action) -> bool) -> Option<Direction> {
+    kp(Action::Up).then_some(Direction::Up)
+}
+
+pub fn rook_move(kp: impl Fn(Action) -> bool) -> Option<Direction> {
+    use Action::{Down, Left, Right, Up};
+    match (kp(Up), kp(Down), kp(Left), kp(Right)) {
+        (true, false, false, false) => Some(Direction::Up),
+        (false, true, false, false) => Some(Direction::Down),
+        (false, false, true, false) => Some(Direction::Left),
+        (false, false, false, true) => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+pub fn bishop_move(kp: impl Fn(Action) -> bool) -> Option<Direction> {
+    use Action::{Down, Left, Right, Up};
+    match (kp(Up), kp(Down), kp(Left), kp(Right)) {
+        (true, false, true, false) => Some(Direction::UpLeft),
+        (true, false, false, true) => Some(Direction::UpRight),
+        (false, true, false, true) => Some(Direction::DownRight),
+        (false, true, true, false) => Some(Direction::DownLeft),
+        _ => None,
+    }
+}
+
+pub fn knight_move(kp: impl Fn(Action) -> bool) -> Option<Direction> {
+    if kp(Action::UpLeftWide) {
+        Some(Direction::UpLeftWide)
+    } else if kp(Action::UpLeftNarrow) {
+        Some(Direction::UpLeftNarrow)
+    } else if kp(Action::UpRightNarrow) {
+        Some(Direction::UpRightNarrow)
+    } else if kp(Action::UpRightWide) {
+        Some(Direction::UpRightWide)
+    } else if kp(Action::DownLeftWide) {
+        Some(Direction::DownLeftWide)
+    } else if kp(Action::DownLeftNarrow) {
+        Some(Direction::DownLeftNarrow)
+    } else if kp(Action::DownRightNarrow) {
+        Some(Direction::DownRightNarrow)
+    } else if kp(Action::DownRightWide) {
+        Some(Direction::DownRightWide)
+    } else {
+        None
+    }
+}
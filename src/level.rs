@@ -0,0 +1,703 @@
+//! Handcrafted levels, written as small text files:
+//!
+//! ```text
+//! # comments start with a hash
+//! name = Walls
+//! size = 8
+//! piece = rook
+//! goal = survive 30        # or `reach top`, `targets 5`, or `none` to play until caught
+//! spawns = random          # or `scripted` for the timeline only
+//! spawn = 2.5, 3, bishop, 0.8   # seconds in, column, piece, seconds per move
+//! map
+//! ........
+//! ..#..#..
+//! ...P....
+//! ```
+//!
+//! The map has one row per line from the top: `.` floor, `#` wall, `O`
+//! hole, `~` ice, a digit for a teleporter joined to the other with the
+//! same digit, and `P` for the player's start. Outside the map, `\#` is a
+//! hash that doesn't start a comment.
+
+use bevy::prelude::*;
+use nanorand::Rng;
+use std::fmt;
+
+use crate::board::{Board, BoardPos, TileType};
+use crate::pieces::{Piece, Player};
+use crate::spawner::SpawnOpponent;
+use crate::terrain::Terrain;
+use crate::{GameEntity, GameOver, GameRng, NewGame, RunStats, MAX_OPP_SPEED, N_TILES, SQUARE_LEN};
+
+// targets aren't put closer than this many moves of a king to the player
+const TARGET_DISTANCE: usize = 3;
+/// Slowest a scripted opponent may move, in seconds per move.
+pub const MAX_SPAWN_SPEED: f32 = MAX_OPP_SPEED * 10.;
+
+const ENDLESS: &str = include_str!("../assets/levels/endless.txt");
+const CAMPAIGN: [&str; 5] = [
+    include_str!("../assets/levels/01-first-steps.txt"),
+    include_str!("../assets/levels/02-walls.txt"),
+    include_str!("../assets/levels/03-the-climb.txt"),
+    include_str!("../assets/levels/04-black-ice.txt"),
+    include_str!("../assets/levels/05-portals.txt"),
+];
+
+/// What the player has to do to beat a level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Goal {
+    /// Stay alive for this many seconds.
+    Survive(f32),
+    /// Get the player onto the top row.
+    ReachTop,
+    /// Step onto this many targets, which appear one at a time.
+    Targets(u32),
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Goal::Survive(secs) => write!(f, "Survive {secs:.0}s"),
+            Goal::ReachTop => write!(f, "Reach the top row"),
+            Goal::Targets(1) => write!(f, "Reach the target"),
+            Goal::Targets(targets) => write!(f, "Reach {targets} targets"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub name: String,
+    pub piece: Piece,
+    pub start: BoardPos,
+    /// None plays until the player is caught.
+    pub goal: Option<Goal>,
+    pub random_spawns: bool,
+    /// Scripted spawns and the second of the run they happen at.
+    pub timeline: Vec<(f32, SpawnOpponent)>,
+    pub terrain: Vec<(BoardPos, Terrain)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LevelError {
+    pub line: usize,
+    pub kind: LevelErrorKind,
+}
+
+/// What is wrong with a level file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelErrorKind {
+    /// A line outside the map that isn't `name = value`.
+    NotASetting(String),
+    UnknownSetting(String),
+    UnsupportedSize,
+    UnknownPiece(String),
+    UnknownGoal(String),
+    /// `survive` needs a finite number of seconds above zero.
+    BadSeconds(String),
+    /// `targets` needs a whole number above zero.
+    BadTargets(String),
+    BadSpawns(String),
+    /// A `spawn` without its four fields.
+    BadSpawn(String),
+    /// A spawn time that isn't a finite number of seconds from zero up.
+    BadSpawnTime(String),
+    BadColumn(String),
+    /// A piece that can't play as an opponent.
+    BadOpponent(String),
+    /// Seconds per move above zero and at most `MAX_SPAWN_SPEED`.
+    BadSpeed(String),
+    MissingMap,
+    /// A second `map` section.
+    SecondMap,
+    /// The map ended after this many rows.
+    ShortMap(usize),
+    /// A map row this many tiles wide.
+    RowWidth(usize),
+    UnknownTile(char),
+    MissingStart,
+    /// A second `P` on the map.
+    SecondStart,
+    /// A teleporter without exactly two ends, and how many it has.
+    TeleporterEnds(u8, usize),
+}
+
+impl fmt::Display for LevelErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelErrorKind::NotASetting(line) => write!(f, "expected `name = value`, got {line:?}"),
+            LevelErrorKind::UnknownSetting(name) => write!(f, "unknown setting {name:?}"),
+            LevelErrorKind::UnsupportedSize => {
+                write!(f, "only {N_TILES}x{N_TILES} boards are supported")
+            }
+            LevelErrorKind::UnknownPiece(piece) => write!(f, "unknown piece {piece:?}"),
+            LevelErrorKind::UnknownGoal(goal) => write!(f, "unknown goal {goal:?}"),
+            LevelErrorKind::BadSeconds(secs) => write!(f, "bad number of seconds {secs:?}"),
+            LevelErrorKind::BadTargets(targets) => {
+                write!(f, "bad number of targets {targets:?}")
+            }
+            LevelErrorKind::BadSpawns(value) => {
+                write!(f, "spawns must be random or scripted, got {value:?}")
+            }
+            LevelErrorKind::BadSpawn(value) => {
+                write!(
+                    f,
+                    "spawn needs seconds, column, piece and speed, got {value:?}"
+                )
+            }
+            LevelErrorKind::BadSpawnTime(at) => write!(f, "bad spawn time {at:?}"),
+            LevelErrorKind::BadColumn(col) => {
+                write!(f, "column must be 0 to {}, got {col:?}", N_TILES - 1)
+            }
+            LevelErrorKind::BadOpponent(piece) => write!(f, "{piece:?} can't be an opponent"),
+            LevelErrorKind::BadSpeed(speed) => {
+                write!(
+                    f,
+                    "seconds per move must be above 0 and at most {MAX_SPAWN_SPEED}, got {speed:?}"
+                )
+            }
+            LevelErrorKind::MissingMap => write!(f, "missing map"),
+            LevelErrorKind::SecondMap => write!(f, "only one map is allowed"),
+            LevelErrorKind::ShortMap(rows) => write!(f, "map has {rows} rows, expected {N_TILES}"),
+            LevelErrorKind::RowWidth(width) => {
+                write!(f, "map row is {width} tiles wide, expected {N_TILES}")
+            }
+            LevelErrorKind::UnknownTile(tile) => write!(f, "unknown map tile {tile:?}"),
+            LevelErrorKind::MissingStart => write!(f, "map has no player start `P`"),
+            LevelErrorKind::SecondStart => write!(f, "map has more than one player start `P`"),
+            LevelErrorKind::TeleporterEnds(id, ends) => {
+                write!(f, "teleporter {id} has {ends} ends, expected 2")
+            }
+        }
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+// the line up to any comment, with `\#` read as a hash
+fn strip_comment(line: &str) -> String {
+    let mut stripped = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'#') => stripped.push(chars.next().unwrap_or(c)),
+            '#' => break,
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// Whether a level can have the player start as the piece.
+pub fn playable(piece: Piece) -> bool {
+    !piece.player_moves().is_empty()
+}
+
+/// Whether a level can spawn the piece as an opponent, which only
+/// pieces that can move down the board do.
+pub fn spawnable(piece: Piece) -> bool {
+    !piece.opp_moves().is_empty()
+}
+
+// "seconds, column, piece, seconds per move"
+fn parse_spawn(value: &str) -> Result<(f32, SpawnOpponent), LevelErrorKind> {
+    let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+    let [at, col, piece, speed] = fields[..] else {
+        return Err(LevelErrorKind::BadSpawn(value.to_string()));
+    };
+    let at = at
+        .parse()
+        .ok()
+        .filter(|at: &f32| at.is_finite() && *at >= 0.)
+        .ok_or_else(|| LevelErrorKind::BadSpawnTime(at.to_string()))?;
+    let spawn = SpawnOpponent {
+        col: col
+            .parse()
+            .ok()
+            .filter(|col| *col < N_TILES)
+            .ok_or_else(|| LevelErrorKind::BadColumn(col.to_string()))?,
+        piece: Piece::from_name(piece)
+            .filter(|piece| spawnable(*piece))
+            .ok_or_else(|| LevelErrorKind::BadOpponent(piece.to_string()))?,
+        speed: speed
+            .parse()
+            .ok()
+            .filter(|speed: &f32| speed.is_finite() && *speed > 0. && *speed <= MAX_SPAWN_SPEED)
+            .ok_or_else(|| LevelErrorKind::BadSpeed(speed.to_string()))?,
+    };
+    Ok((at, spawn))
+}
+
+impl Level {
+    pub fn parse(text: &str) -> Result<Self, LevelError> {
+        let mut level = Level {
+            name: String::new(),
+            piece: Piece::Rook,
+            start: BoardPos::new(0, 0),
+            goal: None,
+            random_spawns: true,
+            timeline: Vec::new(),
+            terrain: Vec::new(),
+        };
+        let mut start = None;
+        let mut map_line = None;
+        let mut lines = text.lines().enumerate().map(|(n, line)| (n + 1, line));
+        let mut last_line = 0;
+        while let Some((n, line)) = lines.next() {
+            last_line = n;
+            let err = |kind: LevelErrorKind| LevelError { line: n, kind };
+            let line = strip_comment(line);
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "map" {
+                if map_line.replace(n).is_some() {
+                    return Err(err(LevelErrorKind::SecondMap));
+                }
+                for y in 0..N_TILES {
+                    let Some((n, row)) = lines.next() else {
+                        return Err(LevelError {
+                            line: last_line,
+                            kind: LevelErrorKind::ShortMap(y),
+                        });
+                    };
+                    last_line = n;
+                    let err = |kind: LevelErrorKind| LevelError { line: n, kind };
+                    let row: Vec<char> = row.trim().chars().collect();
+                    if row.len() != N_TILES {
+                        return Err(err(LevelErrorKind::RowWidth(row.len())));
+                    }
+                    for (x, tile) in row.into_iter().enumerate() {
+                        let pos = BoardPos::new(x, y);
+                        let terrain = match tile {
+                            '.' => continue,
+                            'P' => {
+                                if start.replace(pos).is_some() {
+                                    return Err(err(LevelErrorKind::SecondStart));
+                                }
+                                continue;
+                            }
+                            '#' => Terrain::Wall,
+                            'O' => Terrain::Hole,
+                            '~' => Terrain::Ice,
+                            digit @ '0'..='9' => Terrain::Teleporter(digit as u8 - b'0'),
+                            other => return Err(err(LevelErrorKind::UnknownTile(other))),
+                        };
+                        level.terrain.push((pos, terrain));
+                    }
+                }
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                return Err(err(LevelErrorKind::NotASetting(line.to_string())));
+            };
+            let (name, value) = (name.trim(), value.trim());
+            match name {
+                "name" => level.name = value.to_string(),
+                "size" => {
+                    // the game is drawn and balanced for one board size
+                    if value.parse() != Ok(N_TILES) {
+                        return Err(err(LevelErrorKind::UnsupportedSize));
+                    }
+                }
+                "piece" => {
+                    level.piece = Piece::from_name(value)
+                        .filter(|piece| playable(*piece))
+                        .ok_or_else(|| err(LevelErrorKind::UnknownPiece(value.to_string())))?
+                }
+                "goal" => {
+                    level.goal = match value.split_whitespace().collect::<Vec<_>>()[..] {
+                        ["none"] => None,
+                        ["reach", "top"] => Some(Goal::ReachTop),
+                        ["targets", targets] => Some(Goal::Targets(
+                            targets
+                                .parse()
+                                .ok()
+                                .filter(|targets| *targets > 0)
+                                .ok_or_else(|| {
+                                    err(LevelErrorKind::BadTargets(targets.to_string()))
+                                })?,
+                        )),
+                        ["survive", secs] => Some(Goal::Survive(
+                            secs.parse()
+                                .ok()
+                                .filter(|secs: &f32| secs.is_finite() && *secs > 0.)
+                                .ok_or_else(|| err(LevelErrorKind::BadSeconds(secs.to_string())))?,
+                        )),
+                        _ => return Err(err(LevelErrorKind::UnknownGoal(value.to_string()))),
+                    }
+                }
+                "spawns" => {
+                    level.random_spawns = match value {
+                        "random" => true,
+                        "scripted" => false,
+                        _ => return Err(err(LevelErrorKind::BadSpawns(value.to_string()))),
+                    }
+                }
+                "spawn" => level.timeline.push(parse_spawn(value).map_err(err)?),
+                _ => return Err(err(LevelErrorKind::UnknownSetting(name.to_string()))),
+            }
+        }
+        let Some(map_line) = map_line else {
+            return Err(LevelError {
+                line: last_line,
+                kind: LevelErrorKind::MissingMap,
+            });
+        };
+        level.start = start.ok_or(LevelError {
+            line: map_line,
+            kind: LevelErrorKind::MissingStart,
+        })?;
+        for id in level
+            .terrain
+            .iter()
+            .filter_map(|(_, terrain)| match terrain {
+                Terrain::Teleporter(id) => Some(*id),
+                _ => None,
+            })
+        {
+            let ends = level
+                .terrain
+                .iter()
+                .filter(|(_, terrain)| *terrain == Terrain::Teleporter(id))
+                .count();
+            if ends != 2 {
+                return Err(LevelError {
+                    line: map_line,
+                    kind: LevelErrorKind::TeleporterEnds(id, ends),
+                });
+            }
+        }
+        level.timeline.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(level)
+    }
+
+    /// The standard game: a rook in the middle of an empty board.
+    pub fn endless() -> Self {
+        Self::parse(ENDLESS).unwrap_or_else(|err| panic!("endless.txt: {err}"))
+    }
+
+    /// The campaign's levels in order.
+    pub fn campaign() -> Vec<Self> {
+        CAMPAIGN
+            .iter()
+            .enumerate()
+            .map(|(n, text)| {
+                Self::parse(text).unwrap_or_else(|err| panic!("campaign level {}: {err}", n + 1))
+            })
+            .collect()
+    }
+
+    /// Writes the level back out in the format `parse` reads.
+    pub fn to_text(&self) -> String {
+        let goal = match self.goal {
+            None => "none".to_string(),
+            Some(Goal::Survive(secs)) => format!("survive {secs}"),
+            Some(Goal::ReachTop) => "reach top".to_string(),
+            Some(Goal::Targets(targets)) => format!("targets {targets}"),
+        };
+        let spawns = if self.random_spawns {
+            "random"
+        } else {
+            "scripted"
+        };
+        let mut text = format!(
+            "name = {}\nsize = {N_TILES}\npiece = {}\ngoal = {goal}\nspawns = {spawns}\n",
+            self.name.replace('#', "\\#"),
+            self.piece.name(),
+        );
+        for (at, spawn) in &self.timeline {
+            text += &format!(
+                "spawn = {at}, {}, {}, {}\n",
+                spawn.col,
+                spawn.piece.name(),
+                spawn.speed
+            );
+        }
+        text += "map\n";
+        for y in 0..N_TILES {
+            for x in 0..N_TILES {
+                let pos = BoardPos::new(x, y);
+                let terrain = self
+                    .terrain
+                    .iter()
+                    .find(|(at, _)| *at == pos)
+                    .map(|(_, terrain)| *terrain);
+                text.push(match terrain {
+                    _ if pos == self.start => 'P',
+                    None | Some(Terrain::Floor) => '.',
+                    Some(Terrain::Wall) => '#',
+                    Some(Terrain::Hole) => 'O',
+                    Some(Terrain::Ice) => '~',
+                    Some(Terrain::Teleporter(id)) => char::from(b'0' + id),
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn new_game(&self) -> NewGame {
+        NewGame {
+            piece: self.piece,
+            bot: None,
+            assisted: false,
+            start: self.start,
+            collisions: default(),
+            pawn: None,
+            power_ups: false,
+            terrain: self.terrain.clone(),
+            goal: self.goal,
+            timeline: self.timeline.clone(),
+            random_spawns: self.random_spawns,
+            difficulty: default(),
+            seed: None,
+            gauntlet: false,
+        }
+    }
+}
+
+/// Put on the board entity to play out a level's timeline and goal.
+#[derive(Component, Default)]
+pub struct LevelScript {
+    pub goal: Option<Goal>,
+    pub timeline: Vec<(f32, SpawnOpponent)>,
+    // index of the next timeline entry
+    next: usize,
+}
+
+impl LevelScript {
+    pub fn new(goal: Option<Goal>, timeline: Vec<(f32, SpawnOpponent)>) -> Self {
+        Self {
+            goal,
+            timeline,
+            next: 0,
+        }
+    }
+}
+
+/// Sent once when the player meets the level's goal.
+#[derive(Event)]
+pub struct GameWon;
+
+pub fn run_timeline(
+    stats: Res<RunStats>,
+    game_over: Res<GameOver>,
+    mut scripts: Query<&mut LevelScript>,
+    mut spawns: EventWriter<SpawnOpponent>,
+) {
+    if game_over.0 {
+        return;
+    }
+    for mut script in scripts.iter_mut() {
+        while let Some((at, spawn)) = script.timeline.get(script.next).copied() {
+            if at > stats.elapsed {
+                break;
+            }
+            spawns.send(spawn);
+            script.next += 1;
+        }
+    }
+}
+
+pub fn check_goal(
+    mut game_over: ResMut<GameOver>,
+    mut stats: ResMut<RunStats>,
+    scripts: Query<(&LevelScript, &Board)>,
+    player: Query<Entity, With<Player>>,
+    mut won_writer: EventWriter<GameWon>,
+) {
+    let (Ok((script, board)), Ok(player)) = (scripts.get_single(), player.get_single()) else {
+        return;
+    };
+    let won = match script.goal {
+        _ if game_over.0 => false,
+        None => false,
+        Some(Goal::Survive(secs)) => stats.elapsed >= secs,
+        Some(Goal::ReachTop) => board
+            .find(TileType::Player(player))
+            .is_some_and(|pos| pos.y == 0),
+        Some(Goal::Targets(targets)) => stats.targets >= targets,
+    };
+    if won {
+        game_over.0 = true;
+        stats.won = true;
+        won_writer.send(GameWon);
+    }
+}
+
+/// A tile the player has to reach for a `Goal::Targets`. It sits beside
+/// the board's grid, so opponents pass over it.
+#[derive(Component)]
+pub struct Target {
+    pub pos: BoardPos,
+}
+
+/// Counts targets the player steps on and puts down the next one.
+pub fn update_targets(
+    mut commands: Commands,
+    game_over: Res<GameOver>,
+    mut stats: ResMut<RunStats>,
+    mut rng: ResMut<GameRng>,
+    scripts: Query<(&LevelScript, &Board)>,
+    player: Query<(Entity, &Piece), With<Player>>,
+    targets: Query<(Entity, &Target)>,
+) {
+    let (Ok((script, board)), Ok((player, piece))) = (scripts.get_single(), player.get_single())
+    else {
+        return;
+    };
+    let Some(Goal::Targets(goal)) = script.goal else {
+        return;
+    };
+    let Some(player_pos) = board
+        .find(TileType::Player(player))
+        .filter(|_| !game_over.0)
+    else {
+        return;
+    };
+    let mut placed = false;
+    for (entity, target) in targets.iter() {
+        if target.pos == player_pos {
+            stats.targets += 1;
+            commands.entity(entity).despawn();
+        } else {
+            placed = true;
+        }
+    }
+    if placed || stats.targets >= goal {
+        return;
+    }
+    let distance = |pos: BoardPos| {
+        pos.x
+            .abs_diff(player_pos.x)
+            .max(pos.y.abs_diff(player_pos.y))
+    };
+    let reachable = |pos: &BoardPos| {
+        board.terrain(*pos) == Terrain::Floor
+            // a bishop never leaves its colour
+            && (*piece != Piece::Bishop || (pos.x + pos.y) % 2 == (player_pos.x + player_pos.y) % 2)
+    };
+    let mut options: Vec<BoardPos> = BoardPos::all()
+        .filter(|pos| reachable(pos) && distance(*pos) >= TARGET_DISTANCE)
+        .collect();
+    if options.is_empty() {
+        options = BoardPos::all()
+            .filter(|pos| reachable(pos) && *pos != player_pos)
+            .collect();
+    }
+    if options.is_empty() {
+        return;
+    }
+    let pos = options[rng.0.generate_range(0..options.len())];
+    let mut translation = Board::coord_to_vec(pos);
+    // above the terrain, under the pieces
+    translation.z = 0.35;
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::srgba(1., 0.8, 0.2, 0.8),
+                custom_size: Some(Vec2::splat(SQUARE_LEN * 0.5)),
+                ..default()
+            },
+            transform: Transform::from_translation(translation),
+            ..default()
+        },
+        Target { pos },
+        GameEntity,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str =
+        "map\n........\n........\n........\n...P....\n........\n........\n........\n........\n";
+
+    fn error(text: &str) -> LevelErrorKind {
+        Level::parse(text).unwrap_err().kind
+    }
+
+    #[test]
+    fn names_with_a_hash_survive_a_round_trip() {
+        let mut level = Level::parse(&format!("name = Plain\n{MAP}")).unwrap();
+        level.name = "Level #2 \\ the sequel".to_string();
+        assert_eq!(Level::parse(&level.to_text()).unwrap().name, level.name);
+    }
+
+    #[test]
+    fn one_start_and_one_map() {
+        let two_starts = MAP.replacen("........", "P.......", 1);
+        assert_eq!(error(&two_starts), LevelErrorKind::SecondStart);
+        assert_eq!(error(&format!("{MAP}{MAP}")), LevelErrorKind::SecondMap);
+        assert_eq!(
+            Level::parse(&format!("name = Twice\n{MAP}{MAP}"))
+                .unwrap_err()
+                .line,
+            11
+        );
+    }
+
+    #[test]
+    fn survive_needs_positive_seconds() {
+        for secs in ["-5", "0", "soon"] {
+            assert_eq!(
+                error(&format!("goal = survive {secs}\n{MAP}")),
+                LevelErrorKind::BadSeconds(secs.to_string())
+            );
+        }
+        let level = Level::parse(&format!("goal = survive 12.5\n{MAP}")).unwrap();
+        assert_eq!(level.goal, Some(Goal::Survive(12.5)));
+    }
+
+    #[test]
+    fn survive_needs_finite_seconds() {
+        for secs in ["inf", "NaN", "1e39"] {
+            assert_eq!(
+                error(&format!("goal = survive {secs}\n{MAP}")),
+                LevelErrorKind::BadSeconds(secs.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn spawns_say_which_field_is_wrong() {
+        let spawn = |value: &str| error(&format!("spawn = {value}\n{MAP}"));
+        assert_eq!(
+            spawn("1, 3, rook"),
+            LevelErrorKind::BadSpawn("1, 3, rook".to_string())
+        );
+        for at in ["-1", "inf", "NaN"] {
+            assert_eq!(
+                spawn(&format!("{at}, 3, rook, 0.8")),
+                LevelErrorKind::BadSpawnTime(at.to_string())
+            );
+        }
+        assert_eq!(
+            spawn("1, 8, rook, 0.8"),
+            LevelErrorKind::BadColumn("8".to_string())
+        );
+        assert_eq!(
+            spawn("1, 3, pawn, 0.8"),
+            LevelErrorKind::BadOpponent("pawn".to_string())
+        );
+        for speed in ["0", "inf", "NaN", "1e30", "12.5"] {
+            assert_eq!(
+                spawn(&format!("1, 3, rook, {speed}")),
+                LevelErrorKind::BadSpeed(speed.to_string())
+            );
+        }
+        let level = Level::parse(&format!("spawn = 2.5, 3, bishop, 12\n{MAP}")).unwrap();
+        assert_eq!(level.timeline.len(), 1);
+        assert_eq!(level.timeline[0].0, 2.5);
+        assert_eq!(level.timeline[0].1.speed, 12.);
+    }
+}
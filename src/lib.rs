@@ -0,0 +1,504 @@
+#![allow(dead_code)]
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+pub mod achievements;
+pub mod assist;
+pub mod board;
+pub mod bots;
+pub mod campaign;
+pub mod controls;
+pub mod daily;
+pub mod editor;
+pub mod gamepad;
+pub mod gauntlet;
+pub mod input;
+pub mod level;
+pub mod menu;
+pub mod overlay;
+pub mod pawn;
+pub mod pieces;
+pub mod pointer;
+pub mod powerups;
+pub mod scores;
+pub mod spawner;
+pub mod storage;
+pub mod telemetry;
+pub mod terrain;
+pub mod timeattack;
+pub mod tutorial;
+
+use bevy::prelude::*;
+use nanorand::Rng;
+use std::collections::HashMap;
+
+use board::{
+    Board, BoardError, BoardPos, Collision, Direction, Edge, Move, MoveReq, MoveResult,
+    OppCollision, OppTimer, TileType, ToDelete,
+};
+use bots::{bot_input, Bot};
+use gauntlet::{advance_gauntlet, Gauntlet, TierReached};
+use level::{check_goal, run_timeline, update_targets, GameWon, Goal, Level, LevelScript};
+use pawn::{promote_pawns, PawnChallenge};
+use pieces::{
+    sprite_or_default, BoardSprite, Opponent, Piece, PieceSprites, Player, PlayerPiece, Side,
+};
+use powerups::{
+    collect_power_ups, expire_power_ups, opponent_time_scale, spawn_power_ups, PowerUpSpawner,
+    PowerUpTable, Shielded, SlowTime,
+};
+use spawner::{spawn_opp_pieces, spawn_requested, Difficulty, SpawnOpponent, Spawner};
+use terrain::{spawn_terrain, Terrain};
+
+pub const SCREEN_LEN: f32 = 300. * SCALE;
+pub const N_TILES: usize = 8;
+pub const SCALE: f32 = 2.5;
+pub const SQUARE_LEN: f32 = 32. * SCALE;
+pub const TILE_GAP: f32 = 2. * SCALE;
+pub const TILE_DIS: f32 = TILE_GAP + SQUARE_LEN;
+pub const FROM_ORIGIN: f32 = TILE_DIS / 2.;
+
+pub const PLAYER_MOVE_SPEED: f32 = 0.15;
+
+pub const PLAYER_SIDE: Side = Side::Black;
+pub const OPP_SIDE: Side = Side::White;
+
+pub const MAX_SPAWN_DUR: f32 = 1.5;
+pub const MIN_SPAWN_DUR: f32 = 0.6;
+pub const SPAWN_DUR_DECR: f32 = 0.1;
+
+// min is faster than max
+pub const MAX_OPP_SPEED: f32 = 1.2;
+pub const MIN_OPP_SPEED: f32 = 0.4;
+pub const OPP_SPEED_DECR: f32 = 0.05;
+// score seconds per opponent in a chain collision the player set off
+pub const CHAIN_BONUS: f32 = 5.;
+
+/// Game logic shared by the windowed game and the headless runner.
+/// Nothing is on the board until a `NewGame` event is sent. Keyboard
+/// input is not included: add `player_input` to `GameSet::Input` when
+/// a human can play.
+pub struct GamePlugin;
+
+/// Clears the current game, if any, and starts a fresh one.
+#[derive(Event, Clone)]
+pub struct NewGame {
+    pub piece: Piece,
+    pub bot: Option<Bot>,
+    // assisted runs don't count towards high scores
+    pub assisted: bool,
+    pub start: BoardPos,
+    pub collisions: OppCollision,
+    // starts the player as a pawn on the bottom row of the start column
+    pub pawn: Option<PawnChallenge>,
+    // pickups that turn the player into another piece for a while
+    pub power_ups: bool,
+    // walls, holes, ice and teleporters; the start tile should be floor
+    pub terrain: Vec<(BoardPos, Terrain)>,
+    // what wins the game, None to play until caught
+    pub goal: Option<Goal>,
+    // scripted spawns and the second they happen at
+    pub timeline: Vec<(f32, SpawnOpponent)>,
+    // false leaves spawning to `SpawnOpponent` events
+    pub random_spawns: bool,
+    pub difficulty: Difficulty,
+    // reseeds `GameRng` so the same spawns come in the same order
+    pub seed: Option<u64>,
+    // moves up a tier of new mechanics every `TIER_SECS`
+    pub gauntlet: bool,
+}
+
+/// The endless level, see `assets/levels/endless.txt`.
+impl Default for NewGame {
+    fn default() -> Self {
+        Level::endless().new_game()
+    }
+}
+
+/// Sent once when the player's piece is taken.
+#[derive(Event)]
+pub struct GameEnded;
+
+/// Marks everything that belongs to a single game so it can be cleared.
+#[derive(Component)]
+pub struct GameEntity;
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    Input,
+    Logic,
+}
+
+#[derive(Resource)]
+pub struct GameOver(pub bool);
+
+#[derive(Resource)]
+pub struct GameRng(pub nanorand::pcg64::Pcg64);
+
+impl GameRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self(nanorand::pcg64::Pcg64::new_seed(seed as u128))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self(nanorand::pcg64::Pcg64::new())
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct RunStats {
+    pub elapsed: f32,
+    /// Seconds added to the score for opponents in chains the player set off.
+    pub bonus: f32,
+    /// Times the player promoted in the pawn challenge.
+    pub promotions: u32,
+    pub death_by: Option<Piece>,
+    /// Where the player was taken.
+    pub death_at: Option<BoardPos>,
+    /// The level's goal; runs with one aren't scored.
+    pub goal: Option<Goal>,
+    /// Targets reached towards a `Goal::Targets`.
+    pub targets: u32,
+    pub won: bool,
+    pub assisted: bool,
+    pub bot: bool,
+}
+
+impl RunStats {
+    pub fn score(&self) -> f32 {
+        self.elapsed + self.bonus
+    }
+}
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MoveReq>()
+            .add_event::<Move>()
+            .add_event::<Collision>()
+            .add_event::<ToDelete>()
+            .add_event::<NewGame>()
+            .add_event::<GameEnded>()
+            .add_event::<GameWon>()
+            .add_event::<TierReached>()
+            .add_event::<SpawnOpponent>()
+            .add_event::<BoardError>()
+            .init_resource::<GameRng>()
+            .init_resource::<RunStats>()
+            .insert_resource(PowerUpTable::load())
+            .insert_resource(GameOver(false))
+            .configure_sets(
+                FixedUpdate,
+                (GameSet::Input, GameSet::Logic)
+                    .chain()
+                    .run_if(any_with_component::<Board>),
+            )
+            .add_systems(PreUpdate, start_game)
+            .add_systems(Update, log_board_errors)
+            .add_systems(FixedUpdate, bot_input.in_set(GameSet::Input))
+            .add_systems(
+                FixedUpdate,
+                (
+                    opp_move,
+                    update_board,
+                    merge_pieces,
+                    spawn_opp_pieces,
+                    run_timeline,
+                    spawn_requested,
+                    move_pieces,
+                    promote_pawns,
+                    collect_power_ups,
+                    // after pickups, so bombed opponents leave the board this tick
+                    clear_pieces,
+                    expire_power_ups,
+                    spawn_power_ups,
+                    tick_run_stats,
+                    advance_gauntlet,
+                    update_targets,
+                    check_goal,
+                    track_opponents,
+                )
+                    .chain()
+                    .in_set(GameSet::Logic),
+            );
+    }
+}
+
+fn start_game(
+    mut commands: Commands,
+    mut new_games: EventReader<NewGame>,
+    game_entities: Query<Entity, With<GameEntity>>,
+    piece_sprites: Option<Res<PieceSprites>>,
+    board_sprite: Option<Res<BoardSprite>>,
+    mut game_over: ResMut<GameOver>,
+    mut stats: ResMut<RunStats>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Some(new_game) = new_games.read().last() else {
+        return;
+    };
+    if let Some(seed) = new_game.seed {
+        *rng = GameRng::seeded(seed);
+    }
+    for entity in game_entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    game_over.0 = false;
+    *stats = RunStats {
+        assisted: new_game.assisted,
+        bot: new_game.bot.is_some(),
+        goal: new_game.goal,
+        ..default()
+    };
+
+    let (start, player_piece) = match &new_game.pawn {
+        Some(_) => (BoardPos::new(new_game.start.x, N_TILES - 1), Piece::Pawn),
+        None => (new_game.start, new_game.piece),
+    };
+    let start_vec = Board::coord_to_vec(start);
+    let mut player = commands.spawn((
+        PlayerPiece::new(
+            sprite_or_default(&piece_sprites, player_piece, PLAYER_SIDE),
+            start_vec,
+            player_piece,
+            PLAYER_MOVE_SPEED,
+        ),
+        GameEntity,
+    ));
+    if let Some(bot) = new_game.bot {
+        player.insert(bot);
+    }
+    if let Some(challenge) = &new_game.pawn {
+        player.insert(challenge.clone());
+    }
+    let player_id = player.id();
+    let mut board = Board::default();
+    board.collisions = new_game.collisions;
+    spawn_terrain(&mut commands, &mut board, &new_game.terrain);
+    board.place_piece(start, TileType::Player(player_id));
+    let mut board_entity = commands.spawn((
+        SpriteBundle {
+            texture: board_sprite
+                .map(|sprite| sprite.0.clone())
+                .unwrap_or_default(),
+            transform: Transform {
+                scale: Vec3::new(SCALE, SCALE, 0.),
+                ..default()
+            },
+            ..default()
+        },
+        board,
+        Spawner::new(new_game.random_spawns, new_game.difficulty),
+        LevelScript::new(new_game.goal, new_game.timeline.clone()),
+        GameEntity,
+    ));
+    if new_game.power_ups {
+        board_entity.insert(PowerUpSpawner::default());
+    }
+    if new_game.gauntlet {
+        board_entity.insert(Gauntlet::default());
+    }
+}
+
+fn opp_move(
+    mut query: Query<(Entity, &mut Opponent, &Piece, &Edge)>,
+    mut move_req_writer: EventWriter<MoveReq>,
+    mut delete_writer: EventWriter<ToDelete>,
+    mut error_writer: EventWriter<BoardError>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+    slowed: Query<&SlowTime>,
+) {
+    let delta = time.delta().mul_f32(opponent_time_scale(&slowed));
+    for (entity, mut opponent, piece, edge) in query.iter_mut() {
+        if opponent.timer.tick(delta).just_finished() {
+            let mut options = edge.moves(*piece);
+            rng.0.shuffle(&mut options);
+            let Some(dir) = options.pop() else {
+                error_writer.send(BoardError::NoMoves(*piece));
+                delete_writer.send(ToDelete { id: entity });
+                continue;
+            };
+            move_req_writer.send(MoveReq {
+                id: TileType::Opponent(entity),
+                mov: dir,
+            });
+        } else {
+            move_req_writer.send(MoveReq {
+                id: TileType::Opponent(entity),
+                mov: Direction::None,
+            });
+        }
+    }
+}
+
+fn update_board(
+    mut commands: Commands,
+    mut query: Query<&mut Board>,
+    pieces: Query<(), With<Piece>>,
+    shielded: Query<Entity, (With<Player>, With<Shielded>)>,
+    game_over: Res<GameOver>,
+    mut stats: ResMut<RunStats>,
+    mut move_req_reader: EventReader<MoveReq>,
+    mut move_writer: EventWriter<Move>,
+    mut collision_writer: EventWriter<Collision>,
+    mut delete_writer: EventWriter<ToDelete>,
+    mut error_writer: EventWriter<BoardError>,
+) {
+    if !game_over.0 {
+        let mut board = query.single_mut();
+        // pieces despawned without going through the board
+        let stale: Vec<Entity> = board
+            .pieces()
+            .map(|(entity, _)| entity)
+            .filter(|entity| !pieces.contains(*entity))
+            .collect();
+        for entity in stale {
+            board.remove(entity);
+            error_writer.send(BoardError::StaleEntity(entity));
+        }
+        board.shielded = !shielded.is_empty();
+        let resolution = board.resolve(move_req_reader.read());
+        if let (false, Ok(player)) = (board.shielded, shielded.get_single()) {
+            commands.entity(player).remove::<Shielded>();
+        }
+        move_writer.send_batch(resolution.moves);
+        for collision in &resolution.collisions {
+            if collision.rule == OppCollision::Chain && collision.by_player {
+                stats.bonus += CHAIN_BONUS * collision.opponents as f32;
+            }
+        }
+        collision_writer.send_batch(resolution.collisions);
+        for error in resolution.errors {
+            // an opponent the board lost track of can't be moved again
+            if let BoardError::MissingPiece(TileType::Opponent(entity)) = error {
+                delete_writer.send(ToDelete { id: entity });
+            }
+            error_writer.send(error);
+        }
+    }
+}
+
+fn move_pieces(
+    mut query: Query<(Entity, &mut Transform, &Piece, Option<&Player>)>,
+    mut game_over: ResMut<GameOver>,
+    mut stats: ResMut<RunStats>,
+    mut move_reader: EventReader<Move>,
+    mut delete_writer: EventWriter<ToDelete>,
+    mut ended_writer: EventWriter<GameEnded>,
+    mut error_writer: EventWriter<BoardError>,
+    board: Query<&Board>,
+) {
+    let mut hash_map: HashMap<Entity, (Mut<'_, Transform>, Piece, Option<&Player>)> =
+        HashMap::new();
+    for (entity_id, transform, piece, player) in query.iter_mut() {
+        hash_map.insert(entity_id, (transform, *piece, player));
+    }
+    for event in move_reader.read() {
+        let entity_id = event.id;
+        let Some(mut entity) = hash_map.remove(&entity_id) else {
+            error_writer.send(BoardError::StaleEntity(entity_id));
+            continue;
+        };
+        match (&event.mov, entity.2) {
+            (MoveResult::NewLoc(vec), _) => entity.0.translation = *vec,
+            (MoveResult::Delete | MoveResult::Captured(_) | MoveResult::Merged(_), None) => {
+                delete_writer.send(ToDelete { id: entity_id });
+            }
+            (MoveResult::Delete | MoveResult::Captured(_) | MoveResult::Merged(_), _) => {
+                if let MoveResult::Captured(by) = event.mov {
+                    stats.death_by = hash_map.get(&by).map(|killer| killer.1);
+                    // the capturer has taken the player's tile by now
+                    stats.death_at = board
+                        .get_single()
+                        .ok()
+                        .and_then(|board| board.find(TileType::Opponent(by)));
+                }
+                // hide the player when the game ends
+                entity.0.translation = Vec3::new(10000., 10000., 0.);
+                if !game_over.0 {
+                    ended_writer.send(GameEnded);
+                }
+                game_over.0 = true;
+            }
+        }
+        hash_map.insert(entity_id, entity);
+    }
+}
+
+// promotes opponents that absorbed another in a merge
+fn merge_pieces(
+    mut pieces: Query<(&mut Piece, &mut Handle<Image>), With<Opponent>>,
+    piece_sprites: Option<Res<PieceSprites>>,
+    mut move_reader: EventReader<Move>,
+) {
+    for event in move_reader.read() {
+        let MoveResult::Merged(into) = event.mov else {
+            continue;
+        };
+        let Ok([(absorbed, _), (mut piece, mut sprite)]) = pieces.get_many_mut([event.id, into])
+        else {
+            continue;
+        };
+        let merged = piece.merged(*absorbed);
+        if merged != *piece {
+            *piece = merged;
+            *sprite = sprite_or_default(&piece_sprites, merged, OPP_SIDE);
+        }
+    }
+}
+
+fn clear_pieces(
+    mut commands: Commands,
+    mut board: Query<&mut Board>,
+    mut delete_reader: EventReader<ToDelete>,
+    mut error_writer: EventWriter<BoardError>,
+) {
+    let mut board = board.single_mut();
+    for event in delete_reader.read() {
+        let entity = **event;
+        board.remove(entity);
+        match commands.get_entity(entity) {
+            Some(mut entity) => entity.despawn(),
+            None => {
+                error_writer.send(BoardError::StaleEntity(entity));
+            }
+        }
+    }
+}
+
+fn log_board_errors(mut errors: EventReader<BoardError>) {
+    for error in errors.read() {
+        log::error!("board error: {error}");
+    }
+}
+
+fn tick_run_stats(time: Res<Time>, game_over: Res<GameOver>, mut stats: ResMut<RunStats>) {
+    if !game_over.0 {
+        stats.elapsed += time.delta_seconds();
+    }
+}
+
+fn track_opponents(
+    time: Res<Time>,
+    mut board: Query<&mut Board>,
+    opponents: Query<(Entity, &Opponent, &Piece, &Edge)>,
+    slowed: Query<&SlowTime>,
+) {
+    // slowed timers take longer in real ticks
+    let tick = time.delta_seconds() * opponent_time_scale(&slowed);
+    let to_ticks = |secs: f32| (secs / tick).ceil() as u32;
+    let mut board = board.single_mut();
+    board.opp_timers.clear();
+    for (entity, opponent, piece, edge) in opponents.iter() {
+        board.opp_timers.insert(
+            entity,
+            OppTimer {
+                piece: *piece,
+                edge: *edge,
+                next_move: to_ticks(opponent.timer.remaining_secs()),
+                period: to_ticks(opponent.timer.duration().as_secs_f32()),
+            },
+        );
+    }
+}
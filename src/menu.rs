@@ -0,0 +1,397 @@
+use bevy::prelude::*;
+
+use crate::achievements::{Achievements, Reward};
+use crate::assist::AssistSettings;
+use crate::board::OppCollision;
+use crate::bots::Bot;
+use crate::daily::Daily;
+use crate::gamepad::PadInput;
+use crate::pawn::{PawnChallenge, PROMOTION_SECS};
+use crate::pieces::Piece;
+use crate::scores::HighScores;
+use crate::spawner::Difficulty;
+use crate::terrain::obstacle_course;
+use crate::{GameOver, NewGame, RunStats};
+
+// seconds the attract mode lingers on a lost game before restarting
+pub const ATTRACT_RESTART: f32 = 1.5;
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    /// A bot plays in the background until the player starts a game.
+    #[default]
+    Title,
+    Playing,
+    Tutorial,
+    Controls,
+    Campaign,
+    Editor,
+    Daily,
+    Achievements,
+    Stats,
+    Gauntlet,
+    TimeAttack,
+}
+
+/// Title screen with attract mode, and the game over prompt.
+pub struct MenuPlugin;
+
+/// Rule options picked on the title screen.
+#[derive(Resource, Default)]
+pub struct GameOptions {
+    pub collisions: OppCollision,
+    /// Pawn challenge promotion, None to play a rook as usual.
+    pub pawn: Option<Piece>,
+    /// Promotions last the rest of the game instead of `PROMOTION_SECS`.
+    pub promotion_forever: bool,
+    pub power_ups: bool,
+    /// Play on `obstacle_course` instead of an empty board.
+    pub obstacles: bool,
+    pub difficulty: Difficulty,
+}
+
+impl GameOptions {
+    fn next_collisions(&mut self) {
+        self.collisions = match self.collisions {
+            OppCollision::Capture => OppCollision::Bounce,
+            OppCollision::Bounce => OppCollision::Merge,
+            OppCollision::Merge => OppCollision::Chain,
+            OppCollision::Chain => OppCollision::Capture,
+        };
+    }
+
+    fn next_pawn(&mut self) {
+        self.pawn = match self.pawn {
+            None => Some(Piece::Rook),
+            Some(Piece::Rook) => Some(Piece::Bishop),
+            Some(Piece::Bishop) => Some(Piece::Knight),
+            Some(Piece::Knight) => Some(Piece::Queen),
+            Some(_) => None,
+        };
+    }
+
+    // hard has to be earned first
+    fn next_difficulty(&mut self, achievements: &Achievements) {
+        self.difficulty = match self.difficulty {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal if achievements.has_reward(Reward::HardMode) => Difficulty::Hard,
+            Difficulty::Normal | Difficulty::Hard => Difficulty::Easy,
+        };
+    }
+
+    fn pawn_challenge(&self) -> Option<PawnChallenge> {
+        let duration = (!self.promotion_forever).then_some(PROMOTION_SECS);
+        self.pawn.map(|piece| PawnChallenge::new(piece, duration))
+    }
+}
+
+fn collisions_name(collisions: OppCollision) -> &'static str {
+    match collisions {
+        OppCollision::Capture => "capture",
+        OppCollision::Bounce => "bounce",
+        OppCollision::Merge => "merge into queens",
+        OppCollision::Chain => "chain destroy",
+    }
+}
+
+/// Which half of the title screen is showing, switched with Tab.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+enum TitlePage {
+    #[default]
+    Modes,
+    /// The rule and assist toggles, whose keys work on either page.
+    Options,
+}
+
+#[derive(Component)]
+struct MenuText;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<AppState>()
+            .init_resource::<GameOptions>()
+            .init_resource::<TitlePage>()
+            .add_systems(Startup, spawn_menu_text)
+            .add_systems(OnEnter(AppState::Title), start_attract)
+            .add_systems(OnEnter(AppState::Playing), start_playing)
+            .add_systems(
+                Update,
+                (
+                    title_input.run_if(in_state(AppState::Title)),
+                    playing_input.run_if(in_state(AppState::Playing)),
+                    update_menu_text,
+                ),
+            );
+    }
+}
+
+fn spawn_menu_text(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 32.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center)
+                .with_background_color(Color::srgba(0., 0., 0., 0.7)),
+                MenuText,
+            ));
+        });
+}
+
+fn start_attract(mut new_game: EventWriter<NewGame>, mut virtual_time: ResMut<Time<Virtual>>) {
+    virtual_time.set_relative_speed(1.);
+    new_game.send(attract_game());
+}
+
+pub fn attract_game() -> NewGame {
+    NewGame {
+        piece: Piece::Rook,
+        bot: Some(Bot::Lookahead),
+        ..default()
+    }
+}
+
+fn start_playing(
+    assist: Res<AssistSettings>,
+    options: Res<GameOptions>,
+    mut new_game: EventWriter<NewGame>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    virtual_time.set_relative_speed(assist.game_speed());
+    new_game.send(player_game(&assist, &options));
+}
+
+fn player_game(assist: &AssistSettings, options: &GameOptions) -> NewGame {
+    NewGame {
+        assisted: assist.any(),
+        collisions: options.collisions,
+        pawn: options.pawn_challenge(),
+        power_ups: options.power_ups,
+        difficulty: options.difficulty,
+        terrain: if options.obstacles {
+            obstacle_course()
+        } else {
+            Vec::new()
+        },
+        ..default()
+    }
+}
+
+fn title_input(
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pad: PadInput,
+    game_over: Res<GameOver>,
+    mut assist: ResMut<AssistSettings>,
+    mut options: ResMut<GameOptions>,
+    mut page: ResMut<TitlePage>,
+    achievements: Res<Achievements>,
+    mut restart: Local<f32>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        *page = match *page {
+            TitlePage::Modes => TitlePage::Options,
+            TitlePage::Options => TitlePage::Modes,
+        };
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyH) {
+        assist.hints = !assist.hints;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyT) {
+        assist.slow = !assist.slow;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyO) {
+        options.next_collisions();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyP) {
+        options.next_pawn();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyK) {
+        options.promotion_forever = !options.promotion_forever;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyU) {
+        options.power_ups = !options.power_ups;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyB) {
+        options.obstacles = !options.obstacles;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyF) {
+        options.next_difficulty(&achievements);
+    }
+    if keyboard_input.just_pressed(KeyCode::Enter) || pad.just_pressed(GamepadButtonType::Start) {
+        next_state.set(AppState::Playing);
+    } else if keyboard_input.just_pressed(KeyCode::KeyL) {
+        next_state.set(AppState::Tutorial);
+    } else if keyboard_input.just_pressed(KeyCode::KeyC) {
+        next_state.set(AppState::Controls);
+    } else if keyboard_input.just_pressed(KeyCode::KeyV) {
+        next_state.set(AppState::Campaign);
+    } else if keyboard_input.just_pressed(KeyCode::KeyE) {
+        next_state.set(AppState::Editor);
+    } else if keyboard_input.just_pressed(KeyCode::KeyD) {
+        next_state.set(AppState::Daily);
+    } else if keyboard_input.just_pressed(KeyCode::KeyA) {
+        next_state.set(AppState::Achievements);
+    } else if keyboard_input.just_pressed(KeyCode::KeyS) {
+        next_state.set(AppState::Stats);
+    } else if keyboard_input.just_pressed(KeyCode::KeyG) {
+        next_state.set(AppState::Gauntlet);
+    } else if keyboard_input.just_pressed(KeyCode::KeyR) {
+        next_state.set(AppState::TimeAttack);
+    } else if game_over.0 {
+        *restart += time.delta_seconds();
+        if *restart > ATTRACT_RESTART {
+            *restart = 0.;
+            new_game.send(attract_game());
+        }
+    }
+}
+
+fn playing_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pad: PadInput,
+    game_over: Res<GameOver>,
+    assist: Res<AssistSettings>,
+    options: Res<GameOptions>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) || pad.just_pressed(GamepadButtonType::Select) {
+        next_state.set(AppState::Title);
+    } else if game_over.0
+        && (keyboard_input.just_pressed(KeyCode::Enter)
+            || pad.just_pressed(GamepadButtonType::Start))
+    {
+        new_game.send(player_game(&assist, &options));
+    }
+}
+
+fn update_menu_text(
+    state: Res<State<AppState>>,
+    game_over: Res<GameOver>,
+    stats: Res<RunStats>,
+    assist: Res<AssistSettings>,
+    options: Res<GameOptions>,
+    page: Res<TitlePage>,
+    high_scores: Res<HighScores>,
+    daily: Res<Daily>,
+    mut query: Query<(&mut Text, &mut Visibility), With<MenuText>>,
+) {
+    let Ok((mut text, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let message = match (state.get(), game_over.0) {
+        (AppState::Title, _) if *page == TitlePage::Modes => [
+            "Chess Dodge".to_string(),
+            "Press Enter or Start to play".to_string(),
+            "L: learn to play".to_string(),
+            format!("D: daily [{}]", daily.date.describe()),
+            "G: gauntlet".to_string(),
+            "R: time attack".to_string(),
+            "V: campaign".to_string(),
+            "E: level editor".to_string(),
+            "A: achievements".to_string(),
+            "S: stats".to_string(),
+            "C: controls".to_string(),
+            "Tab: options".to_string(),
+        ]
+        .join("\n"),
+        (AppState::Title, _) => {
+            let mut lines = vec![
+                "Options".to_string(),
+                format!(
+                    "F: difficulty [{}]",
+                    format!("{:?}", options.difficulty).to_lowercase()
+                ),
+                format!("H: move hints [{}]", on_off(assist.hints)),
+                format!("T: slow time [{}]", on_off(assist.slow)),
+                format!("O: collisions [{}]", collisions_name(options.collisions)),
+                format!("U: power-ups [{}]", on_off(options.power_ups)),
+                format!("B: obstacles [{}]", on_off(options.obstacles)),
+            ];
+            match options.pawn {
+                None => lines.push("P: pawn challenge [off]".to_string()),
+                Some(piece) => {
+                    lines.push(format!(
+                        "P: pawn challenge [promote to {}]",
+                        format!("{piece:?}").to_lowercase()
+                    ));
+                    lines.push(if options.promotion_forever {
+                        "K: promotion lasts [for good]".to_string()
+                    } else {
+                        format!("K: promotion lasts [{PROMOTION_SECS:.0}s]")
+                    });
+                }
+            }
+            lines.push("Tab: back".to_string());
+            lines.join("\n")
+        }
+        (AppState::Playing, true) => {
+            let record = if stats.assisted {
+                "Assisted runs are not scored".to_string()
+            } else {
+                high_scores
+                    .best()
+                    .map_or(String::new(), |best| format!("Best {best:.1}"))
+            };
+            let bonus = if stats.bonus > 0. {
+                format!(
+                    "\nChain bonus +{:.0}, score {:.1}",
+                    stats.bonus,
+                    stats.score()
+                )
+            } else {
+                String::new()
+            };
+            let promotions = match stats.promotions {
+                0 => String::new(),
+                1 => "\nPromoted once".to_string(),
+                n => format!("\nPromoted {n} times"),
+            };
+            format!(
+                "Survived {:.1}s{bonus}{promotions}\n{record}\nEnter: retry  Esc: title",
+                stats.elapsed
+            )
+        }
+        (AppState::Playing, false)
+        | (
+            AppState::Tutorial
+            | AppState::Controls
+            | AppState::Campaign
+            | AppState::Editor
+            | AppState::Daily
+            | AppState::Achievements
+            | AppState::Stats
+            | AppState::Gauntlet
+            | AppState::TimeAttack,
+            _,
+        ) => String::new(),
+    };
+    *visibility = if message.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    if text.sections[0].value != message {
+        text.sections[0].value = message;
+    }
+}
@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+
+use crate::board::{Board, BoardError, BoardPos};
+use crate::SQUARE_LEN;
+
+// how far ahead the overlay looks, in fixed ticks
+const OVERLAY_LOOKAHEAD: u32 = 48;
+
+/// Debug overlay tinting each tile by `Board::threat_map`, toggled with F3.
+pub struct ThreatOverlayPlugin;
+
+#[derive(Component)]
+struct ThreatTile(BoardPos);
+
+#[derive(Resource, Default)]
+pub struct ShowThreats(pub bool);
+
+impl Plugin for ThreatOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowThreats>()
+            .add_systems(Startup, spawn_threat_tiles)
+            .add_systems(Update, (toggle_threats, update_threat_tiles).chain());
+    }
+}
+
+fn spawn_threat_tiles(mut commands: Commands) {
+    for pos in BoardPos::all() {
+        let mut translation = Board::coord_to_vec(pos);
+        // between the board and the pieces
+        translation.z = 0.5;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::NONE,
+                    custom_size: Some(Vec2::splat(SQUARE_LEN)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            ThreatTile(pos),
+        ));
+    }
+}
+
+fn toggle_threats(keyboard_input: Res<ButtonInput<KeyCode>>, mut show: ResMut<ShowThreats>) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        show.0 = !show.0;
+    }
+}
+
+fn update_threat_tiles(
+    show: Res<ShowThreats>,
+    board: Query<&Board>,
+    mut tiles: Query<(&ThreatTile, &mut Sprite, &mut Visibility)>,
+) {
+    let threats = match (show.0, board.get_single()) {
+        (true, Ok(board)) => Some(board.threat_map(OVERLAY_LOOKAHEAD)),
+        _ => None,
+    };
+    for (tile, mut sprite, mut visibility) in tiles.iter_mut() {
+        match threats {
+            Some(threats) => {
+                *visibility = Visibility::Inherited;
+                sprite.color = Color::srgba(1., 0., 0., 0.6 * threats[tile.0.y][tile.0.x]);
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+// board errors listed by the error overlay
+const SHOWN_ERRORS: usize = 6;
+
+/// Lists recent `BoardError`s in a corner of the screen. Only added in
+/// debug builds; release builds just log them.
+pub struct ErrorOverlayPlugin;
+
+#[derive(Component)]
+struct ErrorText;
+
+impl Plugin for ErrorOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        if cfg!(debug_assertions) {
+            app.add_systems(Startup, spawn_error_text)
+                .add_systems(Update, update_error_text);
+        }
+    }
+}
+
+fn spawn_error_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.,
+                color: Color::srgb(1., 0.4, 0.4),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(5.),
+            bottom: Val::Px(5.),
+            ..default()
+        }),
+        ErrorText,
+    ));
+}
+
+fn update_error_text(
+    mut errors: EventReader<BoardError>,
+    mut recent: Local<Vec<String>>,
+    mut total: Local<usize>,
+    mut text: Query<&mut Text, With<ErrorText>>,
+) {
+    let before = *total;
+    for error in errors.read() {
+        *total += 1;
+        recent.push(error.to_string());
+    }
+    if *total == before {
+        return;
+    }
+    let excess = recent.len().saturating_sub(SHOWN_ERRORS);
+    recent.drain(..excess);
+    if let Ok(mut text) = text.get_single_mut() {
+        text.sections[0].value = format!("Board errors: {}\n{}", *total, recent.join("\n"));
+    }
+}
@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+use crate::board::{Board, TileType};
+use crate::pieces::{sprite_or_default, Piece, PieceSprites, Player};
+use crate::powerups::{PowerUp, Transformed};
+use crate::terrain::TerrainSprite;
+use crate::{GameOver, RunStats, PLAYER_SIDE};
+
+// seconds a timed promotion lasts before the player is a pawn again
+pub const PROMOTION_SECS: f32 = 8.;
+
+/// Pawn challenge: the player starts as a pawn on the bottom row that can
+/// only move up. Reaching the top row promotes it to `promote_to`, and the
+/// board is flipped so the player is back at the bottom.
+#[derive(Component, Clone, Debug)]
+pub struct PawnChallenge {
+    pub promote_to: Piece,
+    // None keeps the promotion for the rest of the game
+    pub duration: Option<f32>,
+    // counts down while promoted
+    promoted: Option<Timer>,
+}
+
+impl PawnChallenge {
+    pub fn new(promote_to: Piece, duration: Option<f32>) -> Self {
+        Self {
+            promote_to,
+            duration,
+            promoted: None,
+        }
+    }
+
+    /// Seconds left before a timed promotion wears off.
+    pub fn remaining(&self) -> Option<f32> {
+        self.promoted.as_ref().map(|timer| timer.remaining_secs())
+    }
+}
+
+pub fn promote_pawns(
+    time: Res<Time>,
+    game_over: Res<GameOver>,
+    piece_sprites: Option<Res<PieceSprites>>,
+    mut stats: ResMut<RunStats>,
+    mut board: Query<&mut Board>,
+    // a power-up's piece holds the promotion until it wears off
+    mut players: Query<
+        (Entity, &mut Piece, &mut Handle<Image>, &mut PawnChallenge),
+        (With<Player>, Without<Transformed>),
+    >,
+    mut transforms: Query<&mut Transform, Or<(With<Piece>, With<PowerUp>)>>,
+    mut terrain: Query<&mut Transform, (With<TerrainSprite>, Without<Piece>, Without<PowerUp>)>,
+) {
+    if game_over.0 {
+        return;
+    }
+    let mut board = board.single_mut();
+    for (entity, mut piece, mut sprite, mut challenge) in players.iter_mut() {
+        if let Some(timer) = &mut challenge.promoted {
+            if timer.tick(time.delta()).finished() {
+                challenge.promoted = None;
+                *piece = Piece::Pawn;
+                *sprite = sprite_or_default(&piece_sprites, Piece::Pawn, PLAYER_SIDE);
+            }
+            continue;
+        }
+        let on_top_row = board
+            .find(TileType::Player(entity))
+            .is_some_and(|pos| pos.y == 0);
+        if *piece != Piece::Pawn || !on_top_row {
+            continue;
+        }
+        *piece = challenge.promote_to;
+        *sprite = sprite_or_default(&piece_sprites, challenge.promote_to, PLAYER_SIDE);
+        challenge.promoted = challenge
+            .duration
+            .map(|secs| Timer::from_seconds(secs, TimerMode::Once));
+        stats.promotions += 1;
+
+        // the player goes back to the bottom and opponents are mirrored around it
+        board.flip();
+        for (on_board, pos) in board.pieces().chain(board.power_ups()) {
+            if let Ok(mut transform) = transforms.get_mut(on_board) {
+                transform.translation = Board::coord_to_vec(pos);
+            }
+        }
+        // the board is centred on the origin
+        for mut transform in terrain.iter_mut() {
+            transform.translation.y = -transform.translation.y;
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::board::Direction;
+use crate::SCALE;
+
+pub type PieceSide = (Piece, Side);
+
+#[derive(Resource)]
+pub struct PieceSprites {
+    pub map: HashMap<PieceSide, Handle<Image>>,
+}
+
+#[derive(Resource)]
+pub struct BoardSprite(pub Handle<Image>);
+
+impl PieceSprites {
+    pub fn get(&self, piece: Piece, side: Side) -> Option<Handle<Image>> {
+        self.map.get(&(piece, side)).cloned()
+    }
+}
+
+// headless runs have no sprites loaded, so fall back to an empty handle
+pub fn sprite_or_default(
+    sprites: &Option<Res<PieceSprites>>,
+    piece: Piece,
+    side: Side,
+) -> Handle<Image> {
+    let Some(sprites) = sprites else {
+        return Handle::default();
+    };
+    sprites.get(piece, side).unwrap_or_else(|| {
+        log::warn!("no sprite loaded for {side:?} {piece:?}");
+        Handle::default()
+    })
+}
+
+#[derive(Bundle)]
+pub struct PieceBundle<T: Component> {
+    sprite: SpriteBundle,
+    mob: T,
+    piece: Piece,
+}
+
+pub type OpponentPiece = PieceBundle<Opponent>;
+pub type PlayerPiece = PieceBundle<Player>;
+
+#[derive(Component)]
+pub struct Player {
+    pub timer_dur: f32,
+    pub timer: Timer,
+    pub can_move: bool,
+}
+
+#[derive(Component)]
+pub struct Opponent {
+    pub timer: Timer,
+}
+
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Component, Debug)]
+pub enum Piece {
+    Rook,
+    Bishop,
+    Knight,
+    Pawn,
+    Queen,
+    King,
+}
+
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Side {
+    White,
+    Black,
+}
+
+impl Piece {
+    /// Lowercase name, as used in level and save files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Piece::Rook => "rook",
+            Piece::Bishop => "bishop",
+            Piece::Knight => "knight",
+            Piece::Pawn => "pawn",
+            Piece::Queen => "queen",
+            Piece::King => "king",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Piece> {
+        [
+            Piece::Rook,
+            Piece::Bishop,
+            Piece::Knight,
+            Piece::Pawn,
+            Piece::Queen,
+            Piece::King,
+        ]
+        .into_iter()
+        .find(|piece| piece.name() == name)
+    }
+
+    /// Moves available to the player when playing as this piece.
+    pub fn player_moves(&self) -> &'static [Direction] {
+        use Direction::*;
+        match self {
+            Piece::Pawn => &[Up],
+            Piece::Rook => &[Up, Down, Left, Right],
+            Piece::Bishop => &[UpLeft, UpRight, DownRight, DownLeft],
+            Piece::Queen => &[Up, Down, Left, Right, UpLeft, UpRight, DownRight, DownLeft],
+            Piece::Knight => &[
+                UpLeftWide,
+                UpLeftNarrow,
+                UpRightNarrow,
+                UpRightWide,
+                DownLeftWide,
+                DownLeftNarrow,
+                DownRightNarrow,
+                DownRightWide,
+            ],
+            _ => &[],
+        }
+    }
+
+    /// The piece two merging opponents become: a rook's and a bishop's
+    /// moves together make a queen, so any two of them do.
+    pub fn merged(self, other: Piece) -> Piece {
+        match (self, other) {
+            (
+                Piece::Rook | Piece::Bishop | Piece::Queen,
+                Piece::Rook | Piece::Bishop | Piece::Queen,
+            ) => Piece::Queen,
+            _ => self,
+        }
+    }
+
+    /// Moves an opponent of this piece picks between at random.
+    pub fn opp_moves(&self) -> &'static [Direction] {
+        use Direction::*;
+        match self {
+            Piece::Rook => &[Down],
+            Piece::Bishop => &[DownLeft, DownRight],
+            Piece::Queen => &[DownLeft, Down, DownRight],
+            Piece::Knight => &[DownLeftWide, DownLeftNarrow, DownRightNarrow, DownRightWide],
+            _ => &[],
+        }
+    }
+}
+
+// to-do: organize
+pub trait NewMob {
+    fn new(move_time: f32) -> Self;
+}
+
+impl NewMob for Opponent {
+    fn new(move_time: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(move_time, TimerMode::Repeating),
+        }
+    }
+}
+
+impl NewMob for Player {
+    fn new(move_time: f32) -> Self {
+        Self {
+            timer_dur: move_time,
+            timer: Timer::from_seconds(move_time, TimerMode::Repeating), // to-do: look into
+            can_move: true,
+        }
+    }
+}
+
+impl Player {
+    // ticks the move cooldown, true if the player may move this tick
+    pub fn ready(&mut self, delta: std::time::Duration) -> bool {
+        if self.timer.tick(delta).just_finished() {
+            self.can_move = true;
+        }
+        self.can_move
+    }
+
+    // starts the move cooldown, `timer_dur` long
+    pub fn moved(&mut self) {
+        self.can_move = false;
+        self.timer = Timer::from_seconds(self.timer_dur, TimerMode::Once);
+    }
+}
+
+impl<T: Component + NewMob> PieceBundle<T> {
+    pub fn new(texture: Handle<Image>, coords: Vec3, piece: Piece, move_time: f32) -> Self {
+        Self {
+            sprite: SpriteBundle {
+                texture,
+                transform: Transform {
+                    translation: coords,
+                    scale: Vec3::new(SCALE, SCALE, 1.0),
+                    ..default()
+                },
+                ..default()
+            },
+            mob: T::new(move_time),
+            piece,
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::board::{Board, BoardPos, Direction, TileType};
+use crate::bots::Bot;
+use crate::pieces::{Piece, Player};
+use crate::{GameOver, SQUARE_LEN};
+
+/// Mouse and touch input: tapping one of the player's legal
+/// destinations moves there, and hovering shows where it can go.
+pub struct PointerPlugin;
+
+/// Board tiles under the pointer, read by `player_input`.
+#[derive(Resource, Default)]
+pub struct PointerInput {
+    pub hovered: Option<BoardPos>,
+    /// Last tapped tile, waiting for the player's move cooldown.
+    pub clicked: Option<BoardPos>,
+}
+
+impl PointerInput {
+    /// The move taking `piece` from `pos` to the tapped tile, if it can
+    /// get there once the terrain has had its say.
+    pub fn take_move(&mut self, board: &Board, piece: Piece, pos: BoardPos) -> Option<Direction> {
+        let target = self.clicked.take().filter(|target| *target != pos)?;
+        piece
+            .player_moves()
+            .iter()
+            .copied()
+            .find(|dir| board.land(pos, *dir, true) == Some(target))
+    }
+}
+
+#[derive(Component)]
+struct MoveTile(BoardPos);
+
+impl Plugin for PointerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PointerInput>()
+            .add_systems(Startup, spawn_move_tiles)
+            .add_systems(Update, (track_pointer, update_move_tiles).chain());
+    }
+}
+
+fn spawn_move_tiles(mut commands: Commands) {
+    for pos in BoardPos::all() {
+        let mut translation = Board::coord_to_vec(pos);
+        // above the threat overlay, below the hints
+        translation.z = 0.55;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::NONE,
+                    custom_size: Some(Vec2::splat(SQUARE_LEN)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            MoveTile(pos),
+        ));
+    }
+}
+
+fn track_pointer(
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mut pointer: ResMut<PointerInput>,
+) {
+    let (Ok(window), Ok((camera, camera_transform))) = (window.get_single(), camera.get_single())
+    else {
+        return;
+    };
+    let to_tile = |screen: Vec2| Board::screen_to_coord(camera, camera_transform, screen);
+    // a finger on the screen hovers like the mouse does
+    let touch = touches.iter().next().map(|touch| touch.position());
+    pointer.hovered = touch.or(window.cursor_position()).and_then(to_tile);
+
+    let tap = touches
+        .iter_just_pressed()
+        .next()
+        .map(|touch| touch.position())
+        .or(window
+            .cursor_position()
+            .filter(|_| mouse.just_pressed(MouseButton::Left)));
+    if let Some(tile) = tap.and_then(to_tile) {
+        pointer.clicked = Some(tile);
+    }
+}
+
+fn update_move_tiles(
+    game_over: Res<GameOver>,
+    board: Query<&Board>,
+    player: Query<(Entity, &Piece), (With<Player>, Without<Bot>)>,
+    mut pointer: ResMut<PointerInput>,
+    mut tiles: Query<(&MoveTile, &mut Sprite, &mut Visibility)>,
+) {
+    // legal destinations while a human is playing
+    let moves: Option<Vec<BoardPos>> = match (game_over.0, board.get_single(), player.get_single())
+    {
+        (false, Ok(board), Ok((entity, piece))) => {
+            board.find(TileType::Player(entity)).map(|pos| {
+                piece
+                    .player_moves()
+                    .iter()
+                    .filter_map(|dir| board.land(pos, *dir, true))
+                    .filter(|to| *to != pos)
+                    .collect()
+            })
+        }
+        _ => None,
+    };
+    if moves.is_none() {
+        // don't carry taps on menus into the next game
+        pointer.clicked = None;
+    }
+    for (tile, mut sprite, mut visibility) in tiles.iter_mut() {
+        match (&moves, pointer.hovered) {
+            (Some(moves), Some(hovered)) if moves.contains(&tile.0) => {
+                *visibility = Visibility::Inherited;
+                let alpha = if hovered == tile.0 { 0.45 } else { 0.2 };
+                sprite.color = Color::srgba(1., 1., 1., alpha);
+            }
+            _ => *visibility = Visibility::Hidden,
+        }
+    }
+}
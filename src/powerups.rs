@@ -0,0 +1,546 @@
+use bevy::prelude::*;
+use nanorand::Rng;
+
+use crate::board::{Board, BoardPos, Direction, TileType, ToDelete};
+use crate::pawn::PawnChallenge;
+use crate::pieces::{sprite_or_default, Piece, PieceSprites, Player};
+use crate::terrain::Terrain;
+use crate::{storage, GameEntity, GameOver, GameRng, N_TILES, PLAYER_SIDE, SCALE, SQUARE_LEN};
+
+const TABLE_FILE: &str = "powerups.txt";
+const DEFAULT_TABLE: &str = include_str!("../assets/powerups.txt");
+// longest any time in the table may be, in seconds
+const MAX_TABLE_SECS: f32 = 600.;
+// largest weight a pickup may have, so the weights can't overflow when summed
+const MAX_WEIGHT: u32 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerUpKind {
+    /// Turns the player into the piece for a while.
+    Transform(Piece),
+    /// Absorbs one capture, taking out the opponent instead.
+    Shield,
+    /// Slows every opponent's move timer.
+    SlowTime,
+    /// Clears opponents around the player at once.
+    Bomb,
+    /// Makes every player move a double move, taking a second step the tick after.
+    Dash,
+}
+
+impl PowerUpKind {
+    const ALL: [PowerUpKind; 8] = [
+        PowerUpKind::Transform(Piece::Rook),
+        PowerUpKind::Transform(Piece::Bishop),
+        PowerUpKind::Transform(Piece::Knight),
+        PowerUpKind::Transform(Piece::Queen),
+        PowerUpKind::Shield,
+        PowerUpKind::SlowTime,
+        PowerUpKind::Bomb,
+        PowerUpKind::Dash,
+    ];
+
+    // name in the power-up table
+    fn id(self) -> &'static str {
+        match self {
+            PowerUpKind::Transform(Piece::Rook) => "rook",
+            PowerUpKind::Transform(Piece::Bishop) => "bishop",
+            PowerUpKind::Transform(Piece::Knight) => "knight",
+            PowerUpKind::Transform(Piece::Queen) => "queen",
+            PowerUpKind::Transform(_) => "other",
+            PowerUpKind::Shield => "shield",
+            PowerUpKind::SlowTime => "slow",
+            PowerUpKind::Bomb => "bomb",
+            PowerUpKind::Dash => "dash",
+        }
+    }
+
+    // pickups without a piece sprite are drawn as a coloured square
+    fn color(self) -> Color {
+        match self {
+            PowerUpKind::Transform(_) => Color::srgb(1., 0.85, 0.2),
+            PowerUpKind::Shield => Color::srgb(0.3, 0.6, 1.),
+            PowerUpKind::SlowTime => Color::srgb(0.7, 0.4, 1.),
+            PowerUpKind::Bomb => Color::srgb(1., 0.3, 0.2),
+            PowerUpKind::Dash => Color::srgb(0.3, 0.9, 0.4),
+        }
+    }
+}
+
+/// How often a kind of power-up appears relative to the others, and how
+/// many seconds its effect lasts.
+#[derive(Clone, Copy, Debug)]
+pub struct PowerUpRule {
+    pub kind: PowerUpKind,
+    pub weight: u32,
+    pub secs: f32,
+}
+
+/// Spawn rates and durations, read from `assets/powerups.txt` and then
+/// from `powerups.txt` in the save directory, if there is one.
+#[derive(Resource, Clone, Debug)]
+pub struct PowerUpTable {
+    /// Seconds between pickups appearing.
+    pub every: f32,
+    /// Seconds a pickup waits on the board before vanishing.
+    pub lifetime: f32,
+    /// Fraction of their speed opponents keep during slow time.
+    pub slow_factor: f32,
+    /// Tiles around the player, diagonals included, cleared by a bomb.
+    pub bomb_radius: usize,
+    pub rules: Vec<PowerUpRule>,
+}
+
+impl Default for PowerUpTable {
+    fn default() -> Self {
+        let mut table = Self {
+            every: 12.,
+            lifetime: 8.,
+            slow_factor: 0.5,
+            bomb_radius: 2,
+            rules: Vec::new(),
+        };
+        table.apply(DEFAULT_TABLE);
+        table
+    }
+}
+
+impl PowerUpTable {
+    pub fn load() -> Self {
+        let mut table = Self::default();
+        if let Some(file) = storage::load(TABLE_FILE) {
+            table.apply(&file);
+        }
+        table
+    }
+
+    /// Reads `name = value` lines over the current table. Pickups take a
+    /// weight and a duration, `knight = 2, 10`; other lines take a number.
+    pub fn apply(&mut self, text: &str) {
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                log::warn!("{TABLE_FILE}: skipping {line:?}");
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            let number = parse_number(value);
+            match (name, number) {
+                ("every", Some(secs)) => self.every = secs.clamp(1., MAX_TABLE_SECS),
+                ("lifetime", Some(secs)) => self.lifetime = secs.min(MAX_TABLE_SECS),
+                ("slow_factor", Some(factor)) => self.slow_factor = factor.clamp(0.05, 1.),
+                ("bomb_radius", Some(radius)) => self.bomb_radius = (radius as usize).min(N_TILES),
+                _ => match (
+                    PowerUpKind::ALL.iter().find(|kind| kind.id() == name),
+                    parse_rule(value),
+                ) {
+                    (Some(kind), Some((weight, secs))) => {
+                        self.rules.retain(|rule| rule.kind != *kind);
+                        self.rules.push(PowerUpRule {
+                            kind: *kind,
+                            weight,
+                            secs,
+                        });
+                    }
+                    _ => log::warn!("{TABLE_FILE}: skipping {line:?}"),
+                },
+            }
+        }
+    }
+
+    fn rule(&self, kind: PowerUpKind) -> Option<&PowerUpRule> {
+        self.rules.iter().find(|rule| rule.kind == kind)
+    }
+
+    // a kind picked in proportion to the weights
+    fn pick(&self, rng: &mut GameRng) -> Option<PowerUpRule> {
+        let total: u32 = self.rules.iter().map(|rule| rule.weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = rng.0.generate_range(0..total);
+        self.rules.iter().copied().find(|rule| {
+            let hit = roll < rule.weight;
+            roll = roll.saturating_sub(rule.weight);
+            hit
+        })
+    }
+}
+
+// a finite number that isn't negative
+fn parse_number(value: &str) -> Option<f32> {
+    value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|number| number.is_finite() && *number >= 0.)
+}
+
+// "weight, seconds"
+fn parse_rule(value: &str) -> Option<(u32, f32)> {
+    let (weight, secs) = value.split_once(',')?;
+    let weight: u32 = weight.trim().parse().ok()?;
+    Some((
+        weight.min(MAX_WEIGHT),
+        parse_number(secs)?.min(MAX_TABLE_SECS),
+    ))
+}
+
+/// A pickup on the board, taken by moving the player onto it. An opponent
+/// landing on it destroys it.
+#[derive(Component)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    // last seen tile, to tell who took it once it's off the board
+    pos: BoardPos,
+    lifetime: Timer,
+}
+
+/// Put on the board entity when pickups are on for the game.
+#[derive(Component, Default)]
+pub struct PowerUpSpawner {
+    since_last: f32,
+}
+
+/// A player turned into another piece by a pickup.
+#[derive(Component)]
+pub struct Transformed {
+    // the piece to change back into
+    pub original: Piece,
+    pub timer: Timer,
+}
+
+/// The next capture of the player takes out the opponent instead.
+#[derive(Component)]
+pub struct Shielded {
+    pub timer: Timer,
+}
+
+/// Opponents' move timers run at `factor` speed.
+#[derive(Component)]
+pub struct SlowTime {
+    pub factor: f32,
+    pub timer: Timer,
+}
+
+/// Every player move is followed by a second step in the same direction.
+#[derive(Component)]
+pub struct Dashing {
+    pub timer: Timer,
+    /// The second step of the last move, taken on the next tick.
+    pub follow_up: Option<Direction>,
+}
+
+/// How fast opponents' move timers run, slowed while a player has `SlowTime`.
+pub fn opponent_time_scale(slowed: &Query<&SlowTime>) -> f32 {
+    slowed.iter().map(|slow| slow.factor).fold(1., f32::min)
+}
+
+pub fn spawn_power_ups(
+    mut commands: Commands,
+    time: Res<Time>,
+    game_over: Res<GameOver>,
+    table: Res<PowerUpTable>,
+    piece_sprites: Option<Res<PieceSprites>>,
+    mut rng: ResMut<GameRng>,
+    mut board: Query<(&mut Board, &mut PowerUpSpawner)>,
+) {
+    let Ok((mut board, mut spawner)) = board.get_single_mut() else {
+        return;
+    };
+    if game_over.0 {
+        return;
+    }
+    spawner.since_last += time.delta_seconds();
+    if spawner.since_last < table.every {
+        return;
+    }
+    spawner.since_last = 0.;
+    // not on the top row, where opponents appear
+    let empty: Vec<BoardPos> = BoardPos::all()
+        .filter(|pos| {
+            pos.y > 0
+                && board.tile(*pos) == TileType::Empty
+                && board.terrain(*pos) == Terrain::Floor
+        })
+        .collect();
+    let Some(rule) = table.pick(&mut rng).filter(|_| !empty.is_empty()) else {
+        return;
+    };
+    let pos = empty[rng.0.generate_range(0..empty.len())];
+    let mut translation = Board::coord_to_vec(pos);
+    // under the pieces that pass over it
+    translation.z = 0.8;
+    let sprite = match rule.kind {
+        PowerUpKind::Transform(piece) => SpriteBundle {
+            texture: sprite_or_default(&piece_sprites, piece, PLAYER_SIDE),
+            sprite: Sprite {
+                color: rule.kind.color(),
+                ..default()
+            },
+            transform: Transform {
+                translation,
+                scale: Vec3::new(SCALE * 0.6, SCALE * 0.6, 1.),
+                ..default()
+            },
+            ..default()
+        },
+        _ => SpriteBundle {
+            sprite: Sprite {
+                color: rule.kind.color(),
+                custom_size: Some(Vec2::splat(SQUARE_LEN * 0.35)),
+                ..default()
+            },
+            transform: Transform::from_translation(translation),
+            ..default()
+        },
+    };
+    let power_up = commands
+        .spawn((
+            sprite,
+            PowerUp {
+                kind: rule.kind,
+                pos,
+                lifetime: Timer::from_seconds(table.lifetime, TimerMode::Once),
+            },
+            GameEntity,
+        ))
+        .id();
+    board.set_tile(pos, TileType::PowerUp(power_up));
+}
+
+// hands out pickups the player moved onto and clears taken or expired ones
+pub fn collect_power_ups(
+    mut commands: Commands,
+    time: Res<Time>,
+    game_over: Res<GameOver>,
+    table: Res<PowerUpTable>,
+    piece_sprites: Option<Res<PieceSprites>>,
+    mut board: Query<&mut Board>,
+    mut power_ups: Query<(Entity, &mut PowerUp)>,
+    mut players: Query<(&mut Piece, &mut Handle<Image>, Option<&mut Transformed>), With<Player>>,
+    mut delete_writer: EventWriter<ToDelete>,
+) {
+    if game_over.0 {
+        return;
+    }
+    let mut board = board.single_mut();
+    for (entity, mut power_up) in power_ups.iter_mut() {
+        if let Some(pos) = board.find(TileType::PowerUp(entity)) {
+            power_up.pos = pos;
+            if power_up.lifetime.tick(time.delta()).finished() {
+                board.remove(entity);
+                commands.entity(entity).despawn();
+            }
+            continue;
+        }
+        commands.entity(entity).despawn();
+        let TileType::Player(player) = board.tile(power_up.pos) else {
+            continue;
+        };
+        let Ok((mut piece, mut sprite, transformed)) = players.get_mut(player) else {
+            continue;
+        };
+        let secs = table.rule(power_up.kind).map_or(0., |rule| rule.secs);
+        let timer = Timer::from_seconds(secs, TimerMode::Once);
+        // picking up an effect that's already on restarts its timer
+        match power_up.kind {
+            PowerUpKind::Transform(new_piece) => {
+                match transformed {
+                    Some(mut transformed) => transformed.timer = timer,
+                    None => {
+                        commands.entity(player).insert(Transformed {
+                            original: *piece,
+                            timer,
+                        });
+                    }
+                }
+                *piece = new_piece;
+                *sprite = sprite_or_default(&piece_sprites, new_piece, PLAYER_SIDE);
+            }
+            PowerUpKind::Shield => {
+                commands.entity(player).insert(Shielded { timer });
+            }
+            PowerUpKind::SlowTime => {
+                commands.entity(player).insert(SlowTime {
+                    factor: table.slow_factor,
+                    timer,
+                });
+            }
+            PowerUpKind::Bomb => {
+                let blasted: Vec<Entity> = board
+                    .pieces()
+                    .filter(|(target, at)| {
+                        board.tile(*at) == TileType::Opponent(*target)
+                            && at.x.abs_diff(power_up.pos.x) <= table.bomb_radius
+                            && at.y.abs_diff(power_up.pos.y) <= table.bomb_radius
+                    })
+                    .map(|(target, _)| target)
+                    .collect();
+                for target in blasted {
+                    delete_writer.send(ToDelete { id: target });
+                }
+            }
+            PowerUpKind::Dash => {
+                commands.entity(player).insert(Dashing {
+                    timer,
+                    follow_up: None,
+                });
+            }
+        }
+    }
+}
+
+pub fn expire_power_ups(
+    mut commands: Commands,
+    time: Res<Time>,
+    game_over: Res<GameOver>,
+    piece_sprites: Option<Res<PieceSprites>>,
+    mut transformed: Query<(Entity, &mut Piece, &mut Handle<Image>, &mut Transformed)>,
+    mut shielded: Query<(Entity, &mut Shielded)>,
+    mut slowed: Query<(Entity, &mut SlowTime)>,
+    mut dashing: Query<(Entity, &mut Dashing)>,
+) {
+    if game_over.0 {
+        return;
+    }
+    let delta = time.delta();
+    for (entity, mut piece, mut sprite, mut transformed) in transformed.iter_mut() {
+        if transformed.timer.tick(delta).finished() {
+            *piece = transformed.original;
+            *sprite = sprite_or_default(&piece_sprites, transformed.original, PLAYER_SIDE);
+            commands.entity(entity).remove::<Transformed>();
+        }
+    }
+    for (entity, mut shield) in shielded.iter_mut() {
+        if shield.timer.tick(delta).finished() {
+            commands.entity(entity).remove::<Shielded>();
+        }
+    }
+    for (entity, mut slow) in slowed.iter_mut() {
+        if slow.timer.tick(delta).finished() {
+            commands.entity(entity).remove::<SlowTime>();
+        }
+    }
+    for (entity, mut dash) in dashing.iter_mut() {
+        if dash.timer.tick(delta).finished() {
+            commands.entity(entity).remove::<Dashing>();
+        }
+    }
+}
+
+/// Lists the player's active power-ups and pawn promotion with the
+/// seconds each has left.
+pub struct PowerUpHudPlugin;
+
+#[derive(Component)]
+struct PieceTimerText;
+
+impl Plugin for PowerUpHudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_timer_text)
+            .add_systems(Update, update_timer_text);
+    }
+}
+
+fn spawn_timer_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 28.,
+                color: Color::srgb(1., 0.85, 0.2),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(5.),
+            top: Val::Px(5.),
+            ..default()
+        }),
+        PieceTimerText,
+    ));
+}
+
+fn update_timer_text(
+    game_over: Res<GameOver>,
+    players: Query<
+        (
+            &Piece,
+            Option<&Transformed>,
+            Option<&PawnChallenge>,
+            Option<&Shielded>,
+            Option<&SlowTime>,
+            Option<&Dashing>,
+        ),
+        With<Player>,
+    >,
+    mut text: Query<&mut Text, With<PieceTimerText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    let mut lines = Vec::new();
+    if let (false, Ok((piece, transformed, challenge, shield, slow, dash))) =
+        (game_over.0, players.get_single())
+    {
+        let piece_secs = transformed
+            .map(|transformed| transformed.timer.remaining_secs())
+            .or(challenge.and_then(PawnChallenge::remaining));
+        let effects = [
+            (format!("{piece:?}"), piece_secs),
+            (
+                "Shield".to_string(),
+                shield.map(|shield| shield.timer.remaining_secs()),
+            ),
+            (
+                "Slow time".to_string(),
+                slow.map(|slow| slow.timer.remaining_secs()),
+            ),
+            (
+                "Dash".to_string(),
+                dash.map(|dash| dash.timer.remaining_secs()),
+            ),
+        ];
+        for (name, secs) in effects {
+            if let Some(secs) = secs {
+                lines.push(format!("{name} {secs:.1}s"));
+            }
+        }
+    }
+    let message = lines.join("\n");
+    if text.sections[0].value != message {
+        text.sections[0].value = message;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_skips_numbers_that_arent_finite() {
+        let mut table = PowerUpTable::default();
+        let (every, lifetime) = (table.every, table.lifetime);
+        let shield = table.rule(PowerUpKind::Shield).map(|rule| rule.secs);
+        table.apply("every = NaN\nlifetime = inf\nshield = 1, inf\nbomb_radius = -1");
+        assert_eq!((table.every, table.lifetime), (every, lifetime));
+        assert_eq!(
+            table.rule(PowerUpKind::Shield).map(|rule| rule.secs),
+            shield
+        );
+    }
+
+    #[test]
+    fn table_caps_long_times() {
+        let mut table = PowerUpTable::default();
+        table.apply("lifetime = 1e30\nslow = 4000000000, 1e30\nbomb_radius = 1e9");
+        assert_eq!(table.lifetime, MAX_TABLE_SECS);
+        let slow = table.rule(PowerUpKind::SlowTime).unwrap();
+        assert_eq!((slow.weight, slow.secs), (MAX_WEIGHT, MAX_TABLE_SECS));
+        assert_eq!(table.bomb_radius, N_TILES);
+    }
+}
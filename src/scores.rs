@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+
+use crate::menu::AppState;
+use crate::{storage, GameEnded, RunStats};
+
+const SCORES_FILE: &str = "scores.txt";
+const MAX_SCORES: usize = 10;
+
+/// Best scores in seconds survived plus bonuses, highest first.
+/// Only games started from the title screen count, and assisted, bot
+/// and campaign runs are never recorded.
+#[derive(Resource, Default)]
+pub struct HighScores {
+    pub scores: Vec<f32>,
+}
+
+pub struct ScoresPlugin;
+
+impl Plugin for ScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScores::load())
+            .add_systems(Update, record_score.run_if(in_state(AppState::Playing)));
+    }
+}
+
+impl HighScores {
+    fn load() -> Self {
+        storage::load(SCORES_FILE)
+            .map(|file| Self::parse(file.lines()))
+            .unwrap_or_default()
+    }
+
+    /// One score a line, skipping any that aren't a time.
+    pub fn parse<'a>(lines: impl Iterator<Item = &'a str>) -> Self {
+        let mut scores: Vec<f32> = lines
+            .filter_map(|line| line.trim().parse().ok())
+            .filter(|secs: &f32| secs.is_finite() && *secs >= 0.)
+            .collect();
+        // a hand-edited file may be out of order or too long
+        scores.sort_by(|a, b| b.total_cmp(a));
+        scores.truncate(MAX_SCORES);
+        Self { scores }
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        self.scores
+            .iter()
+            .map(|secs| format!("{secs:.3}"))
+            .collect()
+    }
+
+    fn save(&self) {
+        storage::save(SCORES_FILE, &self.to_lines().join("\n"));
+    }
+
+    /// Adds a score, returning its place in the table if it made it in.
+    pub fn submit(&mut self, secs: f32) -> Option<usize> {
+        let rank = self
+            .scores
+            .iter()
+            .position(|best| secs > *best)
+            .unwrap_or(self.scores.len());
+        if rank >= MAX_SCORES {
+            return None;
+        }
+        self.scores.insert(rank, secs);
+        self.scores.truncate(MAX_SCORES);
+        Some(rank)
+    }
+
+    pub fn best(&self) -> Option<f32> {
+        self.scores.first().copied()
+    }
+}
+
+fn record_score(
+    mut ended: EventReader<GameEnded>,
+    stats: Res<RunStats>,
+    mut high_scores: ResMut<HighScores>,
+) {
+    for _ in ended.read() {
+        if !stats.assisted
+            && !stats.bot
+            && stats.goal.is_none()
+            && high_scores.submit(stats.score()).is_some()
+        {
+            high_scores.save();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_survive_a_round_trip() {
+        let mut table = HighScores::default();
+        for secs in [12.5, 30.25, 7.125] {
+            table.submit(secs);
+        }
+        let lines = table.to_lines();
+        let read = HighScores::parse(lines.iter().map(String::as_str));
+        assert_eq!(read.scores, vec![30.25, 12.5, 7.125]);
+    }
+
+    #[test]
+    fn bad_lines_are_skipped() {
+        let file = "12.5\nsoon\n\ninf\nNaN\n-3\n40";
+        assert_eq!(HighScores::parse(file.lines()).scores, vec![40., 12.5]);
+        let long: Vec<String> = (0..20).map(|n| n.to_string()).collect();
+        let read = HighScores::parse(long.iter().map(String::as_str));
+        assert_eq!(read.scores.len(), MAX_SCORES);
+        assert_eq!(read.best(), Some(19.));
+    }
+}
@@ -0,0 +1,311 @@
+use bevy::prelude::*;
+use nanorand::Rng;
+
+use crate::board::{Board, BoardError, BoardPos, Edge, Move, MoveResult, TileType};
+use crate::pieces::{sprite_or_default, OpponentPiece, Piece, PieceSprites};
+use crate::powerups::Shielded;
+use crate::{
+    GameEntity, GameOver, GameRng, MAX_OPP_SPEED, MAX_SPAWN_DUR, MIN_OPP_SPEED, MIN_SPAWN_DUR,
+    N_TILES, OPP_SIDE, OPP_SPEED_DECR, SPAWN_DUR_DECR,
+};
+
+/// The shortest spawn or move timer, however far a boost pushes the ramp.
+pub const MIN_TIMER_SECS: f32 = 0.05;
+
+/// Places an opponent on the top row outside the random spawner, for
+/// scripted sequences. Ignored if the tile already holds an opponent.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SpawnOpponent {
+    pub col: usize,
+    pub piece: Piece,
+    pub speed: f32,
+}
+
+/// How fast the spawner's ramp starts and how far it goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    /// Sparser, slower opponents to begin with.
+    Easy,
+    #[default]
+    Normal,
+    /// Starts a good way up the ramp.
+    Hard,
+}
+
+impl Difficulty {
+    // (seconds between spawns, seconds per opponent move) at the start
+    fn start(self) -> (f32, f32) {
+        match self {
+            Difficulty::Easy => (MAX_SPAWN_DUR + 0.5, MAX_OPP_SPEED + 0.3),
+            Difficulty::Normal => (MAX_SPAWN_DUR, MAX_OPP_SPEED),
+            Difficulty::Hard => (MAX_SPAWN_DUR - 0.4, MAX_OPP_SPEED - 0.3),
+        }
+    }
+
+    // the fastest spawns and opponent moves the ramp reaches
+    fn limits(self) -> (f32, f32) {
+        match self {
+            Difficulty::Easy => (MIN_SPAWN_DUR + 0.3, MIN_OPP_SPEED + 0.2),
+            Difficulty::Normal => (MIN_SPAWN_DUR, MIN_OPP_SPEED),
+            Difficulty::Hard => (MIN_SPAWN_DUR - 0.1, MIN_OPP_SPEED - 0.1),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Spawner {
+    // false when only scripted spawns should appear
+    pub random: bool,
+    pub difficulty: Difficulty,
+    pub timer: Timer,
+    pub cur_duration: f32,
+    pub cur_piece_speed: f32,
+    pub spawn_count: [u32; N_TILES],
+    /// Chance weights of each randomly spawned piece.
+    pub mix: [(Piece, u32); 4],
+    /// Edges random opponents come in from, picked between evenly.
+    pub edges: Vec<Edge>,
+    /// Seconds taken off the fastest spawns and opponent moves the ramp reaches.
+    pub boost: (f32, f32),
+}
+
+impl Default for Spawner {
+    fn default() -> Self {
+        Spawner {
+            random: true,
+            difficulty: Difficulty::Normal,
+            timer: Timer::from_seconds(0.0, TimerMode::Once),
+            cur_duration: MAX_SPAWN_DUR,
+            cur_piece_speed: MAX_OPP_SPEED,
+            spawn_count: [0; N_TILES],
+            mix: [
+                (Piece::Queen, 1),
+                (Piece::Bishop, 4),
+                (Piece::Rook, 12),
+                (Piece::Knight, 0),
+            ],
+            edges: vec![Edge::Top],
+            boost: (0., 0.),
+        }
+    }
+}
+
+impl Spawner {
+    pub fn new(random: bool, difficulty: Difficulty) -> Self {
+        let (cur_duration, cur_piece_speed) = difficulty.start();
+        Spawner {
+            random,
+            difficulty,
+            cur_duration,
+            cur_piece_speed,
+            ..default()
+        }
+    }
+
+    /// Moves the spawns and opponent moves one step up the ramp, towards
+    /// the difficulty's limits less any boost.
+    pub fn step_ramp(&mut self) {
+        let (min_duration, min_speed) = self.difficulty.limits();
+        let (min_duration, min_speed) = (min_duration - self.boost.0, min_speed - self.boost.1);
+        if self.cur_duration > min_duration {
+            self.cur_duration -= SPAWN_DUR_DECR;
+        }
+        if self.cur_piece_speed > min_speed {
+            self.cur_piece_speed -= OPP_SPEED_DECR;
+        }
+    }
+
+    /// Seconds until the next random spawn.
+    pub fn spawn_secs(&self) -> f32 {
+        self.cur_duration.max(MIN_TIMER_SECS)
+    }
+
+    /// Seconds per move of the fastest opponents spawned now.
+    pub fn move_secs(&self) -> f32 {
+        self.cur_piece_speed.max(MIN_TIMER_SECS)
+    }
+
+    fn spawn_chance_array(&self, accessible: &[usize]) -> [u32; N_TILES] {
+        // to-do: think about adding value to max to increase randomness
+        let max = self.spawn_count.iter().max().unwrap() + 1;
+        let mut spawn_chances = [0; N_TILES];
+        let mut prev = 0;
+        for (col, elem) in self.spawn_count.iter().enumerate().take(N_TILES) {
+            if !accessible.contains(&col) {
+                spawn_chances[col] = prev;
+            } else {
+                let chance = (max - *elem) + prev;
+                spawn_chances[col] = chance;
+                prev = chance;
+            }
+        }
+        spawn_chances
+    }
+
+    fn pick_piece(&self, rng: &mut nanorand::pcg64::Pcg64) -> Piece {
+        // drawn as i32 so seeded games keep the pieces they always had
+        let total: i32 = self.mix.iter().map(|(_, weight)| *weight as i32).sum();
+        let mut num = rng.generate_range(1..=total);
+        for (piece, weight) in self.mix {
+            if num <= weight as i32 {
+                return piece;
+            }
+            num -= weight as i32;
+        }
+        Piece::Rook
+    }
+}
+
+pub fn spawn_opp_pieces(
+    mut query: Query<(&mut Board, &mut Spawner)>,
+    piece_sprites: Option<Res<PieceSprites>>,
+    time: Res<Time>,
+    game_over: Res<GameOver>,
+    mut rng: ResMut<GameRng>,
+    mut commands: Commands,
+    mut move_writer: EventWriter<Move>,
+) {
+    if !game_over.0 {
+        let Ok((mut board, mut spawner)) = query.get_single_mut() else {
+            return;
+        };
+        if spawner.random && spawner.timer.tick(time.delta()).just_finished() {
+            let rng = &mut rng.0;
+            let edge = match spawner.edges.len() {
+                0 | 1 => spawner.edges.first().copied().unwrap_or_default(),
+                n => spawner.edges[rng.generate_range(0..n)],
+            };
+            let mut spawn_locations = vec![];
+            for lane in 0..N_TILES {
+                let pos = edge.tile(lane);
+                if board.terrain(pos).blocks_spawns() {
+                    continue;
+                }
+                match board.tile(pos) {
+                    TileType::Opponent(_) => (),
+                    TileType::Player(_) | TileType::PowerUp(_) | TileType::Empty => {
+                        spawn_locations.push(lane)
+                    }
+                }
+            }
+            if !spawn_locations.is_empty() {
+                // the top edge spreads spawns across columns, the others are a plain pick
+                let spawn_loc = if edge == Edge::Top {
+                    let spawn_chances = spawner.spawn_chance_array(&spawn_locations);
+                    let rand_num = rng.generate_range(
+                        spawn_chances[spawn_locations[0]]..=spawn_chances[N_TILES - 1],
+                    );
+                    let mut spawn_loc = 0;
+                    for (col, elem) in spawn_chances.iter().enumerate().take(N_TILES) {
+                        if *elem >= rand_num {
+                            spawn_loc = col;
+                            break;
+                        }
+                    }
+                    spawner.spawn_count[spawn_loc] += 1;
+                    spawn_loc
+                } else {
+                    spawn_locations[rng.generate_range(0..spawn_locations.len())]
+                };
+                let cur_speed = spawner.move_secs();
+                let offsets = [0.0, 0.3, 0.6, 0.9];
+                let mut possible_speeds = vec![];
+                for offset in offsets {
+                    possible_speeds.push(cur_speed + offset);
+                }
+                rng.shuffle(&mut possible_speeds);
+                let speed = possible_speeds.pop().unwrap();
+                let piece = spawner.pick_piece(rng);
+                place_opponent(
+                    &mut commands,
+                    &mut board,
+                    &piece_sprites,
+                    &mut move_writer,
+                    edge.tile(spawn_loc),
+                    edge,
+                    SpawnOpponent {
+                        col: spawn_loc,
+                        piece,
+                        speed,
+                    },
+                );
+            }
+            spawner.step_ramp();
+            spawner.timer = Timer::from_seconds(spawner.spawn_secs(), TimerMode::Once);
+        }
+    }
+}
+
+pub fn spawn_requested(
+    mut query: Query<&mut Board>,
+    piece_sprites: Option<Res<PieceSprites>>,
+    game_over: Res<GameOver>,
+    mut commands: Commands,
+    mut requests: EventReader<SpawnOpponent>,
+    mut move_writer: EventWriter<Move>,
+    mut error_writer: EventWriter<BoardError>,
+) {
+    let mut board = query.single_mut();
+    for request in requests.read() {
+        let Some(pos) = BoardPos::checked(request.col as i32, 0) else {
+            error_writer.send(BoardError::BadColumn(request.col));
+            continue;
+        };
+        if game_over.0
+            || matches!(board.tile(pos), TileType::Opponent(_))
+            || board.terrain(pos).blocks_spawns()
+        {
+            continue;
+        }
+        place_opponent(
+            &mut commands,
+            &mut board,
+            &piece_sprites,
+            &mut move_writer,
+            pos,
+            Edge::Top,
+            *request,
+        );
+    }
+}
+
+// spawns the opponent on an edge tile, taking the player if it is there,
+// unless the player's shield takes the opponent out first
+fn place_opponent(
+    commands: &mut Commands,
+    board: &mut Board,
+    piece_sprites: &Option<Res<PieceSprites>>,
+    move_writer: &mut EventWriter<Move>,
+    pos: BoardPos,
+    edge: Edge,
+    spawn: SpawnOpponent,
+) {
+    let player = match board.tile(pos) {
+        TileType::Player(player) => Some(player),
+        _ => None,
+    };
+    if let (Some(player), true) = (player, board.shielded) {
+        board.shielded = false;
+        commands.entity(player).remove::<Shielded>();
+        return;
+    }
+    let new_piece = commands
+        .spawn((
+            OpponentPiece::new(
+                sprite_or_default(piece_sprites, spawn.piece, OPP_SIDE),
+                Board::coord_to_vec(pos),
+                spawn.piece,
+                spawn.speed,
+            ),
+            edge,
+            GameEntity,
+        ))
+        .id();
+    if let Some(player) = player {
+        move_writer.send(Move {
+            id: player,
+            mov: MoveResult::Captured(new_piece),
+        });
+    }
+    board.set_tile(pos, TileType::Opponent(new_piece));
+}
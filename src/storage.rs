@@ -0,0 +1,59 @@
+//! Small text files saved between sessions, in the data directory or, on
+//! the web, the browser's local storage. Failures are logged and otherwise
+//! ignored so a read-only build still plays.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn data_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_DATA_HOME") {
+        PathBuf::from(dir).join("chess-dodge")
+    } else if let Some(home) = std::env::var_os("HOME") {
+        PathBuf::from(home).join(".local/share/chess-dodge")
+    } else {
+        PathBuf::from(".")
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(name: &str) -> Option<String> {
+    std::fs::read_to_string(data_dir().join(name)).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(name: &str, contents: &str) {
+    let dir = data_dir();
+    if let Err(err) =
+        std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(dir.join(name), contents))
+    {
+        log::warn!("could not save {name}: {err}");
+    }
+}
+
+// None when the page has no storage, such as with cookies blocked
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+// files share the page's storage with anything else on the site
+#[cfg(target_arch = "wasm32")]
+fn key(name: &str) -> String {
+    format!("chess-dodge/{name}")
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(name: &str) -> Option<String> {
+    local_storage()?.get_item(&key(name)).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(name: &str, contents: &str) {
+    let saved = local_storage().map(|storage| storage.set_item(&key(name), contents));
+    match saved {
+        Some(Ok(())) => (),
+        Some(Err(err)) => log::warn!("could not save {name}: {err:?}"),
+        None => log::warn!("could not save {name}: no local storage"),
+    }
+}
@@ -0,0 +1,524 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::board::{Move, MoveResult};
+use crate::gamepad::PadInput;
+use crate::menu::AppState;
+use crate::pieces::{Opponent, Piece, Player};
+use crate::{storage, GameOver, GameSet, RunStats, N_TILES};
+
+const RUNS_FILE: &str = "runs.txt";
+// oldest runs are dropped past this
+const MAX_RUNS: usize = 500;
+// runs shown on the survival chart
+const RECENT_RUNS: usize = 30;
+const FONT_SIZE: f32 = 18.;
+const BAR_LEN: f32 = 160.;
+const BAR_COLOR: Color = Color::srgb(0.4, 0.7, 1.);
+const BEST_COLOR: Color = Color::srgb(1., 0.8, 0.2);
+
+/// Keeps a record of every run played by a person, and a stats screen
+/// with totals and charts of them. Tutorial and editor test runs aren't kept.
+pub struct TelemetryPlugin;
+
+/// What happened in one run.
+#[derive(Clone, Debug, Default)]
+pub struct RunRecord {
+    pub secs: f32,
+    /// Moves made while playing as each piece.
+    pub moves: HashMap<Piece, u32>,
+    /// None when the run was won or is still going.
+    pub death_by: Option<Piece>,
+    pub death_col: Option<usize>,
+    /// Opponents that appeared, by piece.
+    pub spawns: HashMap<Piece, u32>,
+    /// Left for the title before it ended.
+    pub abandoned: bool,
+}
+
+fn write_counts(counts: &HashMap<Piece, u32>) -> String {
+    let mut counts: Vec<String> = counts
+        .iter()
+        .map(|(piece, n)| format!("{}:{n}", piece.name()))
+        .collect();
+    counts.sort();
+    counts.join("/")
+}
+
+fn read_counts(value: &str) -> HashMap<Piece, u32> {
+    value
+        .split('/')
+        .filter_map(|count| {
+            let (piece, n) = count.split_once(':')?;
+            Some((Piece::from_name(piece)?, n.parse().ok()?))
+        })
+        .collect()
+}
+
+impl RunRecord {
+    // one line of space separated `name=value` pairs
+    fn to_line(&self) -> String {
+        let mut line = format!("secs={:.2}", self.secs);
+        if let Some(piece) = self.death_by {
+            line += &format!(" death={}", piece.name());
+        }
+        if let Some(col) = self.death_col {
+            line += &format!(" col={col}");
+        }
+        line += &format!(
+            " moves={} spawns={}",
+            write_counts(&self.moves),
+            write_counts(&self.spawns)
+        );
+        if self.abandoned {
+            line += " abandoned=1";
+        }
+        line
+    }
+
+    // None for a line without a time, which can't be a run
+    fn from_line(line: &str) -> Option<Self> {
+        let mut record = RunRecord::default();
+        let mut secs = None;
+        for (name, value) in line
+            .split_whitespace()
+            .filter_map(|pair| pair.split_once('='))
+        {
+            match name {
+                "secs" => {
+                    secs = value
+                        .parse()
+                        .ok()
+                        .filter(|secs: &f32| secs.is_finite() && *secs >= 0.)
+                }
+                "death" => record.death_by = Piece::from_name(value),
+                "col" => record.death_col = value.parse().ok().filter(|col| *col < N_TILES),
+                "moves" => record.moves = read_counts(value),
+                "spawns" => record.spawns = read_counts(value),
+                "abandoned" => record.abandoned = value == "1",
+                _ => (),
+            }
+        }
+        record.secs = secs?;
+        Some(record)
+    }
+}
+
+/// Finished runs, oldest first, and the one being played.
+#[derive(Resource, Default)]
+pub struct Telemetry {
+    pub runs: Vec<RunRecord>,
+    current: RunRecord,
+    player: Option<Entity>,
+    last_loc: Option<Vec3>,
+    // the current run is one to keep, and is kept if left before it ends
+    tracking: bool,
+    recorded: bool,
+}
+
+impl Telemetry {
+    fn load() -> Self {
+        let runs = storage::load(RUNS_FILE)
+            .map(|file| file.lines().filter_map(RunRecord::from_line).collect())
+            .unwrap_or_default();
+        Self { runs, ..default() }
+    }
+
+    fn save(&self) {
+        let lines: Vec<String> = self.runs.iter().map(RunRecord::to_line).collect();
+        storage::save(RUNS_FILE, &lines.join("\n"));
+    }
+
+    fn push(&mut self, record: RunRecord) {
+        self.runs.push(record);
+        let excess = self.runs.len().saturating_sub(MAX_RUNS);
+        self.runs.drain(..excess);
+        self.recorded = true;
+        self.save();
+    }
+
+    /// Adds up a count over every run, most first.
+    fn totals(&self, counts: impl Fn(&RunRecord) -> Vec<(Piece, u32)>) -> Vec<(Piece, u32)> {
+        let mut totals: HashMap<Piece, u32> = HashMap::new();
+        for (piece, n) in self.runs.iter().flat_map(counts) {
+            *totals.entry(piece).or_default() += n;
+        }
+        let mut totals: Vec<(Piece, u32)> = totals.into_iter().collect();
+        totals.sort_by_key(|(piece, n)| (std::cmp::Reverse(*n), piece.name()));
+        totals
+    }
+
+    fn average(runs: &[RunRecord]) -> f32 {
+        runs.iter().map(|run| run.secs).sum::<f32>() / runs.len().max(1) as f32
+    }
+
+    fn summary(&self) -> String {
+        let total: f32 = self.runs.iter().map(|run| run.secs).sum();
+        let best = self.runs.iter().map(|run| run.secs).fold(0., f32::max);
+        let recent = &self.runs[self.runs.len().saturating_sub(10)..];
+        let caught = self
+            .runs
+            .iter()
+            .filter(|run| run.death_by.is_some())
+            .count();
+        let abandoned = match self.runs.iter().filter(|run| run.abandoned).count() {
+            0 => String::new(),
+            n => format!(" ({n} abandoned)"),
+        };
+        let killer = self
+            .totals(|run| run.death_by.map(|piece| (piece, 1)).into_iter().collect())
+            .first()
+            .map_or(String::new(), |(piece, n)| {
+                format!("\nCaught most by the {} ({n} of {caught})", piece.name())
+            });
+        format!(
+            "{} runs{abandoned}, {:.0}m {:.0}s played\nAverage {:.1}s  Best {best:.1}s  Last 10 {:.1}s{killer}",
+            self.runs.len(),
+            (total / 60.).floor(),
+            total % 60.,
+            Self::average(&self.runs),
+            Self::average(recent),
+        )
+    }
+}
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Telemetry::load())
+            .add_systems(FixedUpdate, record_run.after(GameSet::Logic))
+            .add_systems(OnEnter(AppState::Stats), enter_stats)
+            .add_systems(OnExit(AppState::Stats), exit_stats)
+            .add_systems(Update, stats_input.run_if(in_state(AppState::Stats)));
+    }
+}
+
+fn record_run(
+    state: Res<State<AppState>>,
+    game_over: Res<GameOver>,
+    stats: Res<RunStats>,
+    player: Query<(Entity, &Piece), With<Player>>,
+    spawned: Query<&Piece, Added<Opponent>>,
+    mut moves: EventReader<Move>,
+    mut telemetry: ResMut<Telemetry>,
+) {
+    let Ok((player, piece)) = player.get_single() else {
+        return;
+    };
+    if telemetry.player != Some(player) {
+        // a new game, keeping the last one if it was left before it ended
+        if telemetry.tracking && !telemetry.recorded {
+            let record = RunRecord {
+                abandoned: true,
+                ..std::mem::take(&mut telemetry.current)
+            };
+            telemetry.push(record);
+        }
+        telemetry.player = Some(player);
+        telemetry.current = default();
+        telemetry.last_loc = None;
+        telemetry.tracking = false;
+        telemetry.recorded = false;
+    }
+    let practice = matches!(state.get(), AppState::Tutorial | AppState::Editor);
+    if stats.bot || practice || telemetry.recorded {
+        moves.clear();
+        return;
+    }
+    telemetry.tracking = true;
+    telemetry.current.secs = stats.elapsed;
+    if game_over.0 {
+        let record = RunRecord {
+            death_by: stats.death_by,
+            death_col: stats.death_at.map(|pos| pos.x),
+            ..std::mem::take(&mut telemetry.current)
+        };
+        telemetry.push(record);
+        moves.clear();
+        return;
+    }
+    // a new location is sent every tick, so only count changes
+    for event in moves.read() {
+        if let MoveResult::NewLoc(loc) = event.mov {
+            if event.id == player {
+                if telemetry.last_loc.is_some_and(|last| last != loc) {
+                    *telemetry.current.moves.entry(*piece).or_default() += 1;
+                }
+                telemetry.last_loc = Some(loc);
+            }
+        }
+    }
+    for piece in spawned.iter() {
+        *telemetry.current.spawns.entry(*piece).or_default() += 1;
+    }
+}
+
+#[derive(Component)]
+struct StatsUi;
+
+fn text(parent: &mut ChildBuilder, value: impl Into<String>) {
+    parent.spawn(TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size: FONT_SIZE,
+            color: Color::WHITE,
+            ..default()
+        },
+    ));
+}
+
+fn bar(parent: &mut ChildBuilder, width: Val, height: Val, color: Color) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            width,
+            height,
+            ..default()
+        },
+        background_color: color.into(),
+        ..default()
+    });
+}
+
+fn column(gap: f32) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(gap),
+            ..default()
+        },
+        ..default()
+    }
+}
+
+/// One labelled horizontal bar per piece.
+fn bar_chart(parent: &mut ChildBuilder, title: &str, counts: &[(Piece, u32)]) {
+    let most = counts.iter().map(|(_, n)| *n).max().unwrap_or(0).max(1);
+    parent.spawn(column(4.)).with_children(|chart| {
+        text(chart, title);
+        if counts.is_empty() {
+            text(chart, "  none yet");
+        }
+        for (piece, n) in counts {
+            chart
+                .spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(6.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(70.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|label| text(label, piece.name()));
+                    bar(
+                        row,
+                        Val::Px(BAR_LEN * *n as f32 / most as f32),
+                        Val::Px(12.),
+                        BAR_COLOR,
+                    );
+                    text(row, n.to_string());
+                });
+        }
+    });
+}
+
+/// Vertical bars side by side, scaled to the tallest, with optional labels underneath.
+fn column_chart(parent: &mut ChildBuilder, title: &str, bars: &[(f32, Color)], labels: &[String]) {
+    let most = bars
+        .iter()
+        .map(|(value, _)| *value)
+        .fold(0., f32::max)
+        .max(f32::EPSILON);
+    parent.spawn(column(4.)).with_children(|chart| {
+        text(chart, title);
+        chart
+            .spawn(NodeBundle {
+                style: Style {
+                    height: Val::Px(60.),
+                    align_items: AlignItems::FlexEnd,
+                    column_gap: Val::Px(2.),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|bars_node| {
+                for (n, (value, color)) in bars.iter().enumerate() {
+                    bars_node.spawn(column(2.)).with_children(|slot| {
+                        // an empty column still shows as a sliver
+                        bar(
+                            slot,
+                            Val::Px(14.),
+                            Val::Px((60. * value / most).max(1.)),
+                            *color,
+                        );
+                        if let Some(label) = labels.get(n) {
+                            text(slot, label.clone());
+                        }
+                    });
+                }
+            });
+    });
+}
+
+fn enter_stats(mut commands: Commands, telemetry: Res<Telemetry>) {
+    let recent = &telemetry.runs[telemetry.runs.len().saturating_sub(RECENT_RUNS)..];
+    let best = recent.iter().map(|run| run.secs).fold(0., f32::max);
+    let survival: Vec<(f32, Color)> = recent
+        .iter()
+        .map(|run| {
+            (
+                run.secs,
+                if run.secs == best {
+                    BEST_COLOR
+                } else {
+                    BAR_COLOR
+                },
+            )
+        })
+        .collect();
+    let mut death_cols = [0.; N_TILES];
+    for col in telemetry.runs.iter().filter_map(|run| run.death_col) {
+        if col < N_TILES {
+            death_cols[col] += 1.;
+        }
+    }
+    let death_cols: Vec<(f32, Color)> = death_cols.iter().map(|n| (*n, BAR_COLOR)).collect();
+    let files: Vec<String> = ('a'..).take(N_TILES).map(String::from).collect();
+    let deaths = telemetry.totals(|run| run.death_by.map(|piece| (piece, 1)).into_iter().collect());
+    let spawns = telemetry.totals(|run| run.spawns.iter().map(|(piece, n)| (*piece, *n)).collect());
+    let moves = telemetry.totals(|run| run.moves.iter().map(|(piece, n)| (*piece, *n)).collect());
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            StatsUi,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(12.),
+                        padding: UiRect::all(Val::Px(16.)),
+                        ..default()
+                    },
+                    background_color: Color::srgba(0., 0., 0., 0.85).into(),
+                    ..default()
+                })
+                .with_children(|panel| {
+                    text(panel, "Stats");
+                    if telemetry.runs.is_empty() {
+                        text(panel, "No runs yet. Play a game and come back.");
+                    } else {
+                        text(panel, telemetry.summary());
+                        panel
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    column_gap: Val::Px(24.),
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|columns| {
+                                columns.spawn(column(12.)).with_children(|left| {
+                                    let title = format!("Survival, last {} runs", recent.len());
+                                    column_chart(left, &title, &survival, &[]);
+                                    column_chart(left, "Caught in column", &death_cols, &files);
+                                });
+                                columns.spawn(column(12.)).with_children(|right| {
+                                    bar_chart(right, "Caught by", &deaths);
+                                    bar_chart(right, "Spawns seen", &spawns);
+                                    bar_chart(right, "Moves as", &moves);
+                                });
+                            });
+                    }
+                    text(panel, "Esc: title");
+                });
+        });
+}
+
+fn exit_stats(mut commands: Commands, ui: Query<Entity, With<StatsUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn stats_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pad: PadInput,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) || pad.just_pressed(GamepadButtonType::Select) {
+        next_state.set(AppState::Title);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_survive_a_round_trip() {
+        let record = RunRecord {
+            secs: 31.25,
+            moves: HashMap::from([(Piece::Rook, 12), (Piece::Queen, 3)]),
+            death_by: Some(Piece::Bishop),
+            death_col: Some(5),
+            spawns: HashMap::from([(Piece::Rook, 20), (Piece::Knight, 1)]),
+            abandoned: false,
+        };
+        let read = RunRecord::from_line(&record.to_line()).unwrap();
+        assert_eq!(read.secs, record.secs);
+        assert_eq!(read.moves, record.moves);
+        assert_eq!(read.death_by, record.death_by);
+        assert_eq!(read.death_col, record.death_col);
+        assert_eq!(read.spawns, record.spawns);
+        assert!(!read.abandoned);
+
+        let left = RunRecord {
+            secs: 4.5,
+            abandoned: true,
+            ..default()
+        };
+        let read = RunRecord::from_line(&left.to_line()).unwrap();
+        assert_eq!(
+            (read.secs, read.death_by, read.abandoned),
+            (4.5, None, true)
+        );
+    }
+
+    #[test]
+    fn bad_lines_are_rejected() {
+        for line in [
+            "",
+            "death=rook col=2",
+            "secs=soon",
+            "secs=inf",
+            "secs=NaN",
+            "secs=-1",
+        ] {
+            assert!(RunRecord::from_line(line).is_none(), "{line:?}");
+        }
+        // unreadable fields are dropped from a run that is otherwise fine
+        let read =
+            RunRecord::from_line("secs=3 death=dragon col=99 moves=rook:x/bishop:2").unwrap();
+        assert_eq!(read.death_by, None);
+        assert_eq!(read.death_col, None);
+        assert_eq!(read.moves, HashMap::from([(Piece::Bishop, 2)]));
+    }
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+
+use crate::board::{Board, BoardPos};
+use crate::{GameEntity, SQUARE_LEN};
+
+/// What a tile is made of, fixed for the whole game.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Terrain {
+    #[default]
+    Floor,
+    /// Nothing moves onto it.
+    Wall,
+    /// Opponents fall in and are destroyed; the player can't step in.
+    Hole,
+    /// A piece landing here slides one more tile the same way.
+    Ice,
+    /// Sends a piece landing here to the other teleporter with the same id.
+    Teleporter(u8),
+}
+
+impl Terrain {
+    /// Opponents can't appear on it.
+    pub fn blocks_spawns(self) -> bool {
+        matches!(self, Terrain::Wall | Terrain::Hole)
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Terrain::Floor => Color::NONE,
+            Terrain::Wall => Color::srgb(0.35, 0.3, 0.3),
+            Terrain::Hole => Color::srgb(0.05, 0.05, 0.05),
+            Terrain::Ice => Color::srgba(0.6, 0.85, 1., 0.6),
+            // pairs get their own shade
+            Terrain::Teleporter(id) => Color::hsla(280. - 40. * id as f32, 0.8, 0.6, 0.7),
+        }
+    }
+}
+
+/// The obstacle layout offered on the title screen.
+pub fn obstacle_course() -> Vec<(BoardPos, Terrain)> {
+    vec![
+        (BoardPos::new(2, 3), Terrain::Wall),
+        (BoardPos::new(5, 3), Terrain::Wall),
+        (BoardPos::new(0, 6), Terrain::Hole),
+        (BoardPos::new(7, 6), Terrain::Hole),
+        (BoardPos::new(3, 5), Terrain::Ice),
+        (BoardPos::new(4, 5), Terrain::Ice),
+        (BoardPos::new(0, 2), Terrain::Teleporter(0)),
+        (BoardPos::new(7, 2), Terrain::Teleporter(0)),
+    ]
+}
+
+/// Marks the sprite drawn for a terrain tile.
+#[derive(Component)]
+pub struct TerrainSprite;
+
+pub fn spawn_terrain(commands: &mut Commands, board: &mut Board, terrain: &[(BoardPos, Terrain)]) {
+    for (pos, kind) in terrain {
+        board.set_terrain(*pos, *kind);
+        let mut translation = Board::coord_to_vec(*pos);
+        // above the board, under the overlays
+        translation.z = 0.3;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(Vec2::splat(SQUARE_LEN)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+            TerrainSprite,
+            GameEntity,
+        ));
+    }
+}
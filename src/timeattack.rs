@@ -0,0 +1,240 @@
+use bevy::prelude::*;
+
+use crate::assist::AssistSettings;
+use crate::gamepad::PadInput;
+use crate::level::{GameWon, Goal};
+use crate::menu::AppState;
+use crate::{storage, GameOver, NewGame, RunStats};
+
+const TIMES_FILE: &str = "timeattack.txt";
+const MAX_TIMES: usize = 10;
+/// Targets to reach in a time attack.
+pub const TARGETS: u32 = 10;
+
+/// Race to reach `TARGETS` targets while opponents spawn as usual,
+/// scored by the time it takes.
+pub struct TimeAttackPlugin;
+
+/// Fastest finishes in seconds, quickest first. Assisted runs aren't kept.
+#[derive(Resource, Default)]
+pub struct BestTimes {
+    pub times: Vec<f32>,
+    /// Place in the table of the last finish, if it made it.
+    pub rank: Option<usize>,
+}
+
+impl BestTimes {
+    fn load() -> Self {
+        let times = storage::load(TIMES_FILE)
+            .map(|file| {
+                file.lines()
+                    .filter_map(|line| line.trim().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { times, rank: None }
+    }
+
+    fn save(&self) {
+        let file: Vec<String> = self.times.iter().map(|secs| format!("{secs:.3}")).collect();
+        storage::save(TIMES_FILE, &file.join("\n"));
+    }
+
+    fn submit(&mut self, secs: f32) -> Option<usize> {
+        let rank = self
+            .times
+            .iter()
+            .position(|best| secs < *best)
+            .unwrap_or(self.times.len());
+        if rank >= MAX_TIMES {
+            return None;
+        }
+        self.times.insert(rank, secs);
+        self.times.truncate(MAX_TIMES);
+        Some(rank)
+    }
+}
+
+#[derive(Component)]
+struct TimeAttackUi;
+
+#[derive(Component)]
+struct TimeAttackText;
+
+#[derive(Component)]
+struct TimeAttackHud;
+
+impl Plugin for TimeAttackPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BestTimes::load())
+            .add_systems(OnEnter(AppState::TimeAttack), enter_time_attack)
+            .add_systems(OnExit(AppState::TimeAttack), exit_time_attack)
+            .add_systems(
+                Update,
+                (record_time, time_attack_input, update_time_attack_text)
+                    .chain()
+                    .run_if(in_state(AppState::TimeAttack)),
+            );
+    }
+}
+
+fn start_time_attack(
+    best: &mut BestTimes,
+    assist: &AssistSettings,
+    new_game: &mut EventWriter<NewGame>,
+    virtual_time: &mut Time<Virtual>,
+) {
+    best.rank = None;
+    virtual_time.set_relative_speed(assist.game_speed());
+    new_game.send(NewGame {
+        assisted: assist.any(),
+        goal: Some(Goal::Targets(TARGETS)),
+        ..default()
+    });
+}
+
+fn enter_time_attack(
+    mut commands: Commands,
+    mut best: ResMut<BestTimes>,
+    assist: Res<AssistSettings>,
+    mut new_game: EventWriter<NewGame>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    start_time_attack(&mut best, &assist, &mut new_game, &mut virtual_time);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            TimeAttackUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 32.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center)
+                .with_background_color(Color::srgba(0., 0., 0., 0.7)),
+                TimeAttackText,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 28.,
+                        color: Color::srgb(1., 0.8, 0.2),
+                        ..default()
+                    },
+                )
+                .with_background_color(Color::srgba(0., 0., 0., 0.5))
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(8.),
+                    ..default()
+                }),
+                TimeAttackHud,
+            ));
+        });
+}
+
+fn exit_time_attack(mut commands: Commands, ui: Query<Entity, With<TimeAttackUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn record_time(mut won: EventReader<GameWon>, stats: Res<RunStats>, mut best: ResMut<BestTimes>) {
+    for _ in won.read() {
+        if !stats.assisted {
+            best.rank = best.submit(stats.elapsed);
+            if best.rank.is_some() {
+                best.save();
+            }
+        }
+    }
+}
+
+fn time_attack_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pad: PadInput,
+    game_over: Res<GameOver>,
+    assist: Res<AssistSettings>,
+    mut best: ResMut<BestTimes>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) || pad.just_pressed(GamepadButtonType::Select) {
+        next_state.set(AppState::Title);
+    } else if game_over.0
+        && (keyboard_input.just_pressed(KeyCode::Enter)
+            || pad.just_pressed(GamepadButtonType::Start))
+    {
+        start_time_attack(&mut best, &assist, &mut new_game, &mut virtual_time);
+    }
+}
+
+fn update_time_attack_text(
+    best: Res<BestTimes>,
+    game_over: Res<GameOver>,
+    stats: Res<RunStats>,
+    mut text: Query<(&mut Text, &mut Visibility), (With<TimeAttackText>, Without<TimeAttackHud>)>,
+    mut hud: Query<&mut Text, With<TimeAttackHud>>,
+) {
+    let (Ok((mut text, mut visibility)), Ok(mut hud)) =
+        (text.get_single_mut(), hud.get_single_mut())
+    else {
+        return;
+    };
+    let progress = format!("Targets {}/{TARGETS}  {:.1}s", stats.targets, stats.elapsed);
+    if hud.sections[0].value != progress {
+        hud.sections[0].value = progress;
+    }
+    let message = match (game_over.0, stats.won) {
+        (false, _) => String::new(),
+        (true, true) => {
+            let record = match (stats.assisted, best.rank) {
+                (true, _) => "Assisted runs are not scored".to_string(),
+                (false, Some(0)) => "Fastest yet!".to_string(),
+                (false, Some(rank)) => format!("#{} fastest", rank + 1),
+                (false, None) => String::new(),
+            };
+            let times: Vec<String> = best
+                .times
+                .iter()
+                .take(5)
+                .enumerate()
+                .map(|(rank, secs)| format!("{}. {secs:.1}s", rank + 1))
+                .collect();
+            format!(
+                "{TARGETS} targets in {:.1}s\n{record}\n{}\nEnter: retry  Esc: title",
+                stats.elapsed,
+                times.join("\n"),
+            )
+        }
+        (true, false) => format!(
+            "Caught after {} of {TARGETS} targets\nEnter: retry  Esc: title",
+            stats.targets
+        ),
+    };
+    *visibility = if message.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    if text.sections[0].value != message {
+        text.sections[0].value = message;
+    }
+}
@@ -0,0 +1,301 @@
+use bevy::prelude::*;
+
+use crate::board::{Board, BoardPos, TileType};
+use crate::controls::KeyBindings;
+use crate::menu::AppState;
+use crate::pieces::{Opponent, Piece, Player};
+use crate::spawner::SpawnOpponent;
+use crate::{GameOver, NewGame, SQUARE_LEN};
+
+// seconds before a step's opponent appears
+const SPAWN_DELAY: f32 = 1.;
+// seconds before a failed step restarts
+const RETRY_DELAY: f32 = 1.;
+
+/// Scripted lessons for each player piece's controls and each
+/// opponent's movement, using fixed spawns instead of the random spawner.
+pub struct TutorialPlugin;
+
+enum Goal {
+    /// Move the player onto the marked tile.
+    Reach(BoardPos),
+    /// Survive until the step's opponent leaves the board.
+    Dodge,
+}
+
+struct Step {
+    prompt: &'static str,
+    piece: Piece,
+    start: BoardPos,
+    goal: Goal,
+    spawn: Option<SpawnOpponent>,
+}
+
+const STEPS: [Step; 7] = [
+    Step {
+        prompt: "You are the black rook.\n{Up} {Left} {Down} {Right} move one tile up, left, down or right.\nReach the green tile.",
+        piece: Piece::Rook,
+        start: BoardPos::new(3, 5),
+        goal: Goal::Reach(BoardPos::new(1, 2)),
+        spawn: None,
+    },
+    Step {
+        prompt: "The bishop moves diagonally.\nHold two keys at once: {Up}+{Left}, {Up}+{Right}, {Down}+{Left} or {Down}+{Right}.\nReach the green tile.",
+        piece: Piece::Bishop,
+        start: BoardPos::new(3, 5),
+        goal: Goal::Reach(BoardPos::new(5, 3)),
+        spawn: None,
+    },
+    Step {
+        prompt: "The knight jumps in an L.\n{UpLeftWide} {UpLeftNarrow} {UpRightNarrow} {UpRightWide} jump up, {DownLeftWide} {DownLeftNarrow} {DownRightNarrow} {DownRightWide} jump down,\nfrom the widest left jump to the widest right.\nReach the green tile.",
+        piece: Piece::Knight,
+        start: BoardPos::new(3, 5),
+        goal: Goal::Reach(BoardPos::new(4, 3)),
+        spawn: None,
+    },
+    Step {
+        prompt: "White rooks only move straight down.\nStep out of its column and let it pass.",
+        piece: Piece::Rook,
+        start: BoardPos::new(3, 5),
+        goal: Goal::Dodge,
+        spawn: Some(SpawnOpponent {
+            col: 3,
+            piece: Piece::Rook,
+            speed: 0.8,
+        }),
+    },
+    Step {
+        prompt: "White bishops move diagonally down,\nleft or right at random. Dodge it.",
+        piece: Piece::Rook,
+        start: BoardPos::new(3, 5),
+        goal: Goal::Dodge,
+        spawn: Some(SpawnOpponent {
+            col: 3,
+            piece: Piece::Bishop,
+            speed: 0.8,
+        }),
+    },
+    Step {
+        prompt: "White queens move down or diagonally down.\nDodge it.",
+        piece: Piece::Rook,
+        start: BoardPos::new(3, 5),
+        goal: Goal::Dodge,
+        spawn: Some(SpawnOpponent {
+            col: 3,
+            piece: Piece::Queen,
+            speed: 0.8,
+        }),
+    },
+    Step {
+        prompt: "White knights jump down in an L at random, over anything between.\nWatch the tiles they can land on, not the ones they pass. Dodge it.",
+        piece: Piece::Rook,
+        start: BoardPos::new(3, 5),
+        goal: Goal::Dodge,
+        spawn: Some(SpawnOpponent {
+            col: 3,
+            piece: Piece::Knight,
+            speed: 0.8,
+        }),
+    },
+];
+
+#[derive(Resource, Default)]
+struct Progress {
+    step: usize,
+    elapsed: f32,
+    spawned: bool,
+    seen_opponent: bool,
+    failed_for: f32,
+    done: bool,
+}
+
+#[derive(Component)]
+struct TutorialUi;
+
+#[derive(Component)]
+struct TutorialText;
+
+#[derive(Component)]
+struct TargetMarker;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Progress>()
+            .add_systems(OnEnter(AppState::Tutorial), enter_tutorial)
+            .add_systems(OnExit(AppState::Tutorial), exit_tutorial)
+            .add_systems(
+                Update,
+                (run_tutorial, update_tutorial_ui)
+                    .chain()
+                    .run_if(in_state(AppState::Tutorial)),
+            );
+    }
+}
+
+fn enter_tutorial(
+    mut commands: Commands,
+    mut progress: ResMut<Progress>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    *progress = Progress::default();
+    start_step(&STEPS[0], &mut new_game);
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::srgba(0.2, 0.9, 0.3, 0.5),
+                custom_size: Some(Vec2::splat(SQUARE_LEN)),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        TargetMarker,
+        TutorialUi,
+    ));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    padding: UiRect::all(Val::Px(10.)),
+                    ..default()
+                },
+                ..default()
+            },
+            TutorialUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 26.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center)
+                .with_background_color(Color::srgba(0., 0., 0., 0.7)),
+                TutorialText,
+            ));
+        });
+}
+
+fn exit_tutorial(mut commands: Commands, ui: Query<Entity, With<TutorialUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn start_step(step: &Step, new_game: &mut EventWriter<NewGame>) {
+    new_game.send(NewGame {
+        piece: step.piece,
+        start: step.start,
+        random_spawns: false,
+        // tutorial runs never reach the high-score table
+        assisted: true,
+        ..default()
+    });
+}
+
+fn run_tutorial(
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    game_over: Res<GameOver>,
+    board: Query<&Board>,
+    player: Query<Entity, With<Player>>,
+    opponents: Query<(), With<Opponent>>,
+    mut progress: ResMut<Progress>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut new_game: EventWriter<NewGame>,
+    mut spawns: EventWriter<SpawnOpponent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape)
+        || (progress.done && keyboard_input.just_pressed(KeyCode::Enter))
+    {
+        next_state.set(AppState::Title);
+        return;
+    }
+    if progress.done {
+        return;
+    }
+    let step = &STEPS[progress.step];
+    if game_over.0 {
+        progress.failed_for += time.delta_seconds();
+        if progress.failed_for > RETRY_DELAY {
+            let step_index = progress.step;
+            *progress = Progress {
+                step: step_index,
+                ..default()
+            };
+            start_step(step, &mut new_game);
+        }
+        return;
+    }
+
+    progress.elapsed += time.delta_seconds();
+    if let (Some(spawn), false) = (step.spawn, progress.spawned) {
+        if progress.elapsed > SPAWN_DELAY {
+            spawns.send(spawn);
+            progress.spawned = true;
+        }
+    }
+    if !opponents.is_empty() {
+        progress.seen_opponent = true;
+    }
+
+    let complete = match step.goal {
+        Goal::Reach(pos) => match (board.get_single(), player.get_single()) {
+            (Ok(board), Ok(player)) => board.find(TileType::Player(player)) == Some(pos),
+            _ => false,
+        },
+        Goal::Dodge => progress.seen_opponent && opponents.is_empty(),
+    };
+    if complete {
+        let next = progress.step + 1;
+        *progress = Progress {
+            step: next,
+            done: next == STEPS.len(),
+            ..default()
+        };
+        if !progress.done {
+            start_step(&STEPS[next], &mut new_game);
+        }
+    }
+}
+
+fn update_tutorial_ui(
+    progress: Res<Progress>,
+    game_over: Res<GameOver>,
+    bindings: Res<KeyBindings>,
+    mut text: Query<&mut Text, With<TutorialText>>,
+    mut marker: Query<(&mut Transform, &mut Visibility), With<TargetMarker>>,
+) {
+    let message = if progress.done {
+        "Tutorial complete!\nPress Enter to return to the title.".to_string()
+    } else if game_over.0 {
+        "Captured! Trying again...".to_string()
+    } else {
+        format!(
+            "Lesson {}/{}\n{}",
+            progress.step + 1,
+            STEPS.len(),
+            bindings.fill_prompt(STEPS[progress.step].prompt)
+        )
+    };
+    if let Ok(mut text) = text.get_single_mut() {
+        if text.sections[0].value != message {
+            text.sections[0].value = message;
+        }
+    }
+    if let Ok((mut transform, mut visibility)) = marker.get_single_mut() {
+        match STEPS.get(progress.step).map(|step| &step.goal) {
+            Some(Goal::Reach(pos)) if !progress.done => {
+                transform.translation = Board::coord_to_vec(*pos).with_z(0.5);
+                *visibility = Visibility::Inherited;
+            }
+            _ => *visibility = Visibility::Hidden,
+        }
+    }
+}